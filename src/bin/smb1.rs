@@ -0,0 +1,838 @@
+/*!
+ * Inspect and edit a Super Mario Bros. rom from the command line.
+ */
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::process;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use smb1_tools::*;
+
+const USAGE: &str = "\
+usage: smb1 [options] <command> [args...]
+
+commands:
+    info <rom>                         show what the rom was identified as
+    levels <rom>                       list every level and the area it uses
+    dump <rom> <level>                 print a level
+    render <rom> <level>               draw a level as text (--ascii) or as
+                                       a picture (--png)
+    export <rom> <level> <file>        write a level's data to a file (as
+                                       json or ron with --format)
+    import <rom> <level> <file> <out>  write a level's data from a file (as
+                                       json or ron with --format) into the
+                                       rom and save it as <out>
+    patch <rom> <out> <addr>=<hex>...  write bytes into the rom and save it as
+                                       <out>, <addr> is a $XXXX CPU address or
+                                       a file offset
+    validate <rom>                     check that every area in the rom parses
+                                       and that the game can handle it
+    diff <rom> <other>                 show what changed in each area (or
+                                       just --level) between two roms
+    space <rom>                        show where the level data is and how
+                                       much room is left for it
+    apply-patch <rom> <patch> <out>    apply an IPS or BPS patch to the rom and
+                                       save it as <out>
+    create-patch <rom> <edited> <out>  write a patch that turns the rom into
+                                       <edited>, as BPS if <out> ends in .bps
+                                       and IPS otherwise
+
+options:
+    -f, --format <format>  output format: text (default), debug, json or ron
+    -u, --unverified       load roms that aren't known good dumps (rom hacks)
+    -a, --ascii            render levels as text (the default)
+    -c, --color            color rendered text with ANSI escape codes
+    -p, --png <file>       render levels as a png image into <file>
+        --palette <file>   draw png images with the colors of a .pal file
+    -l, --level <level>    the level to diff
+    -r, --repack           import levels larger than the data they replace by
+                           moving the other levels' data to make room
+    -h, --help             print this message
+
+levels are given as a level (1-1) or an area (ground-5, underground-2, ...)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Debug,
+    Json,
+    Ron,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+struct RomInfo<'a> {
+    identity: &'a RomIdentity,
+    header: &'a INesHeader,
+    areas: usize,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+struct LevelInfo {
+    level: RomLevel,
+    area: Area,
+    objects: usize,
+    enemies: usize,
+    pipe_pointers: usize,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+struct LevelDiff {
+    level: String,
+    changes: Vec<LevelChange>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+struct ValidateReport<'a> {
+    errors: &'a [String],
+    warnings: &'a [String],
+}
+
+struct Options {
+    format: Format,
+    unverified: bool,
+    color: bool,
+    png: Option<String>,
+    palette: Option<String>,
+    level: Option<String>,
+    repack: bool,
+    command: String,
+    args: Vec<String>,
+}
+
+/// A level by its name (1-1) or any area by its name (ground-5).
+#[derive(Debug, Clone, Copy)]
+enum LevelName {
+    Level(RomLevel),
+    Area(Area),
+}
+
+impl LevelName {
+    fn parse(name: &str) -> Result<Self> {
+        if let Ok(level) = RomLevel::from_name(name) {
+            return Ok(Self::Level(level));
+        }
+        let area = Area::from_name(name)
+            .ok()
+            .with_context(|| format!("unknown level or area: {}", name))?;
+
+        Ok(Self::Area(area))
+    }
+
+    fn get(&self, rom: &Rom) -> Result<Level> {
+        match self {
+            Self::Level(level) => rom.get_level(level),
+            Self::Area(area) => rom.get_area(area),
+        }
+    }
+
+    fn area(&self, rom: &Rom) -> Result<Area> {
+        match self {
+            Self::Level(level) => rom.get_level_area(level),
+            Self::Area(area) => Ok(*area),
+        }
+    }
+
+    /// Write a level, moving the level data around to make room for it if
+    /// `repack` is set.
+    fn set(&self, rom: &mut Rom, level: &Level, repack: bool) -> Result<()> {
+        match (self, repack) {
+            (Self::Level(level_name), false) => {
+                rom.set_level(level_name, level)
+            }
+            (Self::Level(level_name), true) => {
+                rom.set_level_repacked(level_name, level)
+            }
+            (Self::Area(area), false) => rom.set_area(area, level),
+            (Self::Area(area), true) => rom.set_area_repacked(area, level),
+        }
+    }
+}
+
+impl fmt::Display for LevelName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Level(level) => write!(f, "{}", level),
+            Self::Area(area) => write!(f, "{}", area),
+        }
+    }
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1).collect());
+
+    if let Err(err) = run(&options) {
+        eprintln!("smb1: {:#}", err);
+        process::exit(1);
+    }
+}
+
+/// Print an error with the usage and exit.
+fn usage_error(message: &str) -> ! {
+    eprintln!("smb1: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn parse_args(args: Vec<String>) -> Options {
+    let mut format = Format::Text;
+    let mut unverified = false;
+    let mut color = false;
+    let mut png = None;
+    let mut palette = None;
+    let mut level = None;
+    let mut repack = false;
+    let mut positional = vec![];
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-u" | "--unverified" => unverified = true,
+            "-a" | "--ascii" => png = None,
+            "-c" | "--color" => color = true,
+            "-r" | "--repack" => repack = true,
+            "-p" | "--png" => {
+                let Some(path) = args.next() else {
+                    usage_error("--png needs a file");
+                };
+                png = Some(path);
+            }
+            "--palette" => {
+                let Some(path) = args.next() else {
+                    usage_error("--palette needs a file");
+                };
+                palette = Some(path);
+            }
+            "-l" | "--level" => {
+                let Some(name) = args.next() else {
+                    usage_error("--level needs a level");
+                };
+                level = Some(name);
+            }
+            "-f" | "--format" => {
+                let Some(name) = args.next() else {
+                    usage_error("--format needs a value");
+                };
+                format = match name.as_str() {
+                    "text" => Format::Text,
+                    "debug" => Format::Debug,
+                    "json" => Format::Json,
+                    "ron" => Format::Ron,
+                    _ => usage_error(&format!("unknown format: {}", name)),
+                };
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                usage_error(&format!("unknown option: {}", arg))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.is_empty() {
+        usage_error("no command given");
+    }
+    let command = positional.remove(0);
+
+    Options {
+        format,
+        unverified,
+        color,
+        png,
+        palette,
+        level,
+        repack,
+        command,
+        args: positional,
+    }
+}
+
+fn run(options: &Options) -> Result<()> {
+    let args = &options.args;
+    let expected_args = match options.command.as_str() {
+        "info" | "levels" | "validate" | "space" => 1,
+        "dump" | "render" | "diff" => 2,
+        "export" | "apply-patch" | "create-patch" => 3,
+        "import" => 4,
+        "patch" if args.len() >= 3 => args.len(),
+        "patch" => 3,
+        command => usage_error(&format!("unknown command: {}", command)),
+    };
+    if args.len() != expected_args {
+        usage_error(&format!(
+            "{} takes {} arguments, {} given",
+            options.command,
+            expected_args,
+            args.len()
+        ));
+    }
+    let mut rom = load_rom(&args[0], options.unverified)?;
+    match options.command.as_str() {
+        "info" => info(&rom, options.format),
+        "levels" => levels(&rom, options.format),
+        "dump" => dump(&rom, &LevelName::parse(&args[1])?, options.format),
+        "render" => render(&rom, &LevelName::parse(&args[1])?, options),
+        "export" => {
+            export(&rom, &LevelName::parse(&args[1])?, &args[2], options.format)
+        }
+        "import" => import(
+            &mut rom,
+            &LevelName::parse(&args[1])?,
+            &args[2],
+            &args[3],
+            options.format,
+            options.repack,
+        ),
+        "patch" => patch(&mut rom, &args[1], &args[2..]),
+        "validate" => validate(&rom, options.format),
+        "diff" => {
+            // the other rom is usually a hack
+            let other = load_rom(&args[1], true)?;
+            diff(&rom, &other, options.level.as_deref(), options.format)
+        }
+        "space" => space(&rom, options.format),
+        "apply-patch" => apply_patch(&rom, &args[1], &args[2]),
+        "create-patch" => {
+            // edited roms never match a known dump
+            let edited = load_rom(&args[1], true)?;
+            create_patch(&rom, &edited, &args[2])
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn load_rom(path: &str, unverified: bool) -> Result<Rom> {
+    let rom_data =
+        fs::read(path).with_context(|| format!("failed to read {}", path))?;
+
+    if unverified {
+        Rom::new_unverified(rom_data)
+    } else {
+        Rom::new(rom_data)
+    }
+}
+
+/// Print a value as json or ron.
+#[cfg(feature = "serde")]
+fn print_serialized<T: serde::Serialize>(
+    value: &T,
+    format: Format,
+) -> Result<()> {
+    let output = match format {
+        Format::Ron => ron::ser::to_string_pretty(value, Default::default())?,
+        _ => serde_json::to_string_pretty(value)?,
+    };
+    println!("{}", output);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_serialized<T>(_value: &T, _format: Format) -> Result<()> {
+    bail!("json and ron output need the serde feature")
+}
+
+#[cfg(feature = "serde")]
+fn level_to_string(level: &Level, format: Format) -> Result<String> {
+    match format {
+        Format::Ron => level.to_ron(),
+        _ => level.to_json(),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn level_from_str(data: &str, format: Format) -> Result<Level> {
+    match format {
+        Format::Ron => Level::from_ron(data),
+        _ => Level::from_json(data),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn level_to_string(_level: &Level, _format: Format) -> Result<String> {
+    bail!("json and ron levels need the serde feature")
+}
+
+#[cfg(not(feature = "serde"))]
+fn level_from_str(_data: &str, _format: Format) -> Result<Level> {
+    bail!("json and ron levels need the serde feature")
+}
+
+fn info(rom: &Rom, format: Format) -> Result<()> {
+    let info = RomInfo {
+        identity: &rom.identity,
+        header: &rom.header,
+        areas: rom.areas()?.len(),
+    };
+    match format {
+        Format::Text => {}
+        Format::Debug => {
+            println!("{:#?}", info);
+            return Ok(());
+        }
+        Format::Json | Format::Ron => return print_serialized(&info, format),
+    }
+
+    let level_tables = info.identity.profile.level_tables;
+    println!("revision:     {:?}", info.identity.revision);
+    println!("md5:          {}", info.identity.md5);
+    println!("verified:     {}", info.identity.verified);
+    println!("mapper:       {}", info.header.mapper);
+    println!("prg-rom:      {} bytes", info.header.prg_rom_size);
+    println!("chr-rom:      {} bytes", info.header.chr_rom_size);
+    println!("mirroring:    {:?}", info.header.mirroring);
+    println!("tv system:    {:?}", info.header.tv_system);
+    println!(
+        "level tables: ${:04X} (offset {:#x})",
+        level_tables,
+        rom.cpu_address_to_offset(level_tables)
+    );
+    println!("areas:        {}", info.areas);
+
+    Ok(())
+}
+
+fn levels(rom: &Rom, format: Format) -> Result<()> {
+    let mut levels = vec![];
+    for level_name in RomLevel::all() {
+        let area = rom.get_level_area(&level_name)?;
+        let level = rom.get_area(&area)?;
+
+        levels.push(LevelInfo {
+            level: level_name,
+            area,
+            objects: level.object_data.objects.len(),
+            enemies: level.enemy_data.enemies().count(),
+            pipe_pointers: level.enemy_data.pipe_pointers().count(),
+        });
+    }
+
+    match format {
+        Format::Text => {
+            for info in &levels {
+                println!(
+                    "{}  {:<14} objects: {:<3} enemies: {:<3} pipe pointers: {}",
+                    info.level,
+                    info.area.to_string(),
+                    info.objects,
+                    info.enemies,
+                    info.pipe_pointers
+                );
+            }
+        }
+        Format::Debug => println!("{:#?}", levels),
+        Format::Json | Format::Ron => print_serialized(&levels, format)?,
+    }
+
+    Ok(())
+}
+
+fn dump(rom: &Rom, level_name: &LevelName, format: Format) -> Result<()> {
+    let level = level_name.get(rom)?;
+
+    match format {
+        Format::Text => {}
+        Format::Debug => {
+            println!("{:#?}", level);
+            return Ok(());
+        }
+        Format::Json | Format::Ron => {
+            println!("{}", level_to_string(&level, format)?);
+            return Ok(());
+        }
+    }
+
+    let header = &level.level_header;
+    println!("{}", level_name);
+    println!(
+        "header: time {:?}, start {:?}{}, background {:?}, scenery {:?}, \
+         platform {:?}, ground {:?}",
+        header.time,
+        header.start_position,
+        if header.start_autowalk { " (autowalk)" } else { "" },
+        header.background,
+        header.scenery,
+        header.platform,
+        header.ground
+    );
+
+    println!("objects:");
+    let positions = level.object_data.positions();
+    for (object, position) in level.object_data.objects.iter().zip(positions) {
+        println!("    {}  {:?}", format_position(&position), object.kind);
+    }
+
+    println!("enemies:");
+    let positions = level.enemy_data.positions();
+    for (entry, position) in level.enemy_data.entries.iter().zip(positions) {
+        let position = format_position(&position);
+        match entry {
+            LevelEnemyEntry::Enemy(enemy) => println!(
+                "    {}  {:?}{}",
+                position,
+                enemy.kind,
+                if enemy.hard_mode_flag { " (hard mode)" } else { "" }
+            ),
+            LevelEnemyEntry::PipePointer(pipe_pointer) => println!(
+                "    {}  pipe pointer to {} page {} (world {})",
+                position,
+                pipe_pointer.area,
+                pipe_pointer.page,
+                pipe_pointer.world + 1
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn render(rom: &Rom, level_name: &LevelName, options: &Options) -> Result<()> {
+    let level = level_name.get(rom)?;
+    let area = level_name.area(rom)?;
+
+    let Some(path) = &options.png else {
+        let renderer =
+            AsciiRenderer::new(&level, area.area_type, options.color);
+        print!("{}", renderer.render());
+        return Ok(());
+    };
+
+    let master_palette = match &options.palette {
+        Some(palette) => {
+            let bytes = fs::read(palette)
+                .with_context(|| format!("failed to read {}", palette))?;
+            MasterPalette::from_pal(&bytes)?
+        }
+        None => MasterPalette::default(),
+    };
+    let graphics = rom.level_graphics(&area, &level)?;
+    let image =
+        ImageRenderer::new(&level, area.area_type, &graphics, &master_palette)
+            .render();
+
+    fs::write(path, image_to_png(&image)?)
+        .with_context(|| format!("failed to write {}", path))?;
+    println!(
+        "rendered {} ({}x{}) to {}",
+        level_name, image.width, image.height, path
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "png")]
+fn image_to_png(image: &Image) -> Result<Vec<u8>> {
+    image.to_png()
+}
+
+#[cfg(not(feature = "png"))]
+fn image_to_png(_image: &Image) -> Result<Vec<u8>> {
+    bail!("png images need the png feature")
+}
+
+fn format_position(position: &LevelPosition) -> String {
+    format!(
+        "page {:>2} col {:>2} row {:>2}",
+        position.page, position.coordinate.column, position.coordinate.row
+    )
+}
+
+/**
+ * Levels are exported as json or ron documents, or otherwise as they are
+ * stored in the rom: the area data (header and objects, ending with 0xFD)
+ * followed by the enemy data (ending with 0xFF).
+ */
+fn export(
+    rom: &Rom,
+    level_name: &LevelName,
+    path: &str,
+    format: Format,
+) -> Result<()> {
+    let level = level_name.get(rom)?;
+    let bytes = match format {
+        Format::Json | Format::Ron => {
+            level_to_string(&level, format)?.into_bytes()
+        }
+        Format::Text | Format::Debug => {
+            let (area_data, enemy_data) = level.to_bytes();
+            [area_data, enemy_data].concat()
+        }
+    };
+
+    fs::write(path, &bytes)
+        .with_context(|| format!("failed to write {}", path))?;
+    println!("exported {} ({} bytes) to {}", level_name, bytes.len(), path);
+
+    Ok(())
+}
+
+fn import(
+    rom: &mut Rom,
+    level_name: &LevelName,
+    path: &str,
+    out: &str,
+    format: Format,
+    repack: bool,
+) -> Result<()> {
+    let bytes =
+        fs::read(path).with_context(|| format!("failed to read {}", path))?;
+
+    let level = match format {
+        Format::Json | Format::Ron => {
+            level_from_str(&String::from_utf8(bytes)?, format)?
+        }
+        Format::Text | Format::Debug => {
+            // the area data ends after the header and the objects, then 0xFD
+            let object_data =
+                LevelObjectData::from_bytes(bytes.get(2..).unwrap_or(&[]))
+                    .map_err(|err| err.offset_by(2))?;
+            let area_data_len = 2 + object_data.to_bytes().len();
+            let (area_data, enemy_data) = bytes.split_at(area_data_len);
+            Level::from_bytes(area_data, enemy_data)?
+        }
+    };
+
+    level_name.set(rom, &level, repack)?;
+    rom.save(out)?;
+    println!("imported {} from {} into {}", level_name, path, out);
+
+    Ok(())
+}
+
+/// Write bytes like `$9CB4=0001` (CPU address) or `0x1cc4=0001` (file
+/// offset) into the rom.
+fn patch(rom: &mut Rom, out: &str, patches: &[String]) -> Result<()> {
+    for patch in patches {
+        let Some((address, hex)) = patch.split_once('=') else {
+            bail!("invalid patch (expected <addr>=<hex>): {}", patch);
+        };
+
+        let offset = if let Some(address) = address.strip_prefix('$') {
+            let address = u16::from_str_radix(address, 16)
+                .with_context(|| format!("invalid address: {}", address))?;
+            ensure!(address >= 0x8000, "${:04X} is not in PRG-ROM", address);
+            rom.cpu_address_to_offset(address)
+        } else if let Some(offset) = address.strip_prefix("0x") {
+            usize::from_str_radix(offset, 16)
+                .with_context(|| format!("invalid offset: {}", address))?
+        } else {
+            address
+                .parse()
+                .with_context(|| format!("invalid offset: {}", address))?
+        };
+
+        // hex digits are ascii, so the string can be sliced by bytes
+        ensure!(hex.is_ascii(), "invalid hex: {}", hex);
+        ensure!(hex.len() % 2 == 0, "odd number of hex digits: {}", hex);
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .with_context(|| format!("invalid hex: {}", hex))?;
+        let end = offset
+            .checked_add(bytes.len())
+            .filter(|&end| end <= rom.rom_data.len());
+        let Some(end) = end else {
+            bail!("patch {} is past the end of the rom", patch);
+        };
+
+        rom.rom_data[offset..end].copy_from_slice(&bytes);
+    }
+
+    rom.save(out)?;
+    println!("applied {} patches, saved to {}", patches.len(), out);
+
+    Ok(())
+}
+
+fn validate(rom: &Rom, format: Format) -> Result<()> {
+    let mut errors = vec![];
+    let mut warnings = vec![];
+
+    let areas = rom.areas()?;
+    for area in &areas {
+        let level = match rom.get_area(area) {
+            Ok(level) => level,
+            Err(err) => {
+                errors.push(format!("{}: {:#}", area, err));
+                continue;
+            }
+        };
+        for issue in level.validate(&areas) {
+            let message = format!("{}: {}", area, issue);
+            match issue.severity {
+                Severity::Error => errors.push(message),
+                Severity::Warning => warnings.push(message),
+                Severity::Info => (),
+            }
+        }
+    }
+    for level_name in RomLevel::all() {
+        if let Err(err) = rom.get_level(&level_name) {
+            errors.push(format!("{}: {:#}", level_name, err));
+        }
+    }
+
+    let report = ValidateReport { errors: &errors, warnings: &warnings };
+    match format {
+        Format::Text => {
+            if !rom.identity.verified {
+                println!("warning: rom is not a known good dump");
+            }
+            for message in report.warnings.iter().chain(report.errors) {
+                println!("{}", message);
+            }
+        }
+        Format::Debug => println!("{:#?}", report),
+        Format::Json | Format::Ron => print_serialized(&report, format)?,
+    }
+
+    ensure!(errors.is_empty(), "{} errors found", errors.len());
+    if format == Format::Text {
+        println!(
+            "ok: {} areas, {} levels",
+            areas.len(),
+            RomLevel::all().count()
+        );
+    }
+
+    Ok(())
+}
+
+/// Show the level data regions, what's shared and what's free.
+fn space(rom: &Rom, format: Format) -> Result<()> {
+    let report = rom.space_report()?;
+
+    match format {
+        Format::Text => {
+            // ranges are shown as CPU addresses, with an inclusive end
+            let format_range = |range: &Range<Offset>| {
+                let address = |offset| match rom.offset_to_cpu_address(offset) {
+                    Some(address) => format!("${:04X}", address),
+                    None => format!("{:#x}", offset),
+                };
+                format!(
+                    "{}-{} ({} bytes)",
+                    address(range.start),
+                    address(range.end.saturating_sub(1).max(range.start)),
+                    range.len()
+                )
+            };
+
+            for region in report.regions() {
+                println!(
+                    "{}: {}, {} used, {} free",
+                    region.kind,
+                    format_range(&region.range),
+                    region.used(),
+                    region.free()
+                );
+                for range in &region.unused {
+                    println!("    unused: {}", format_range(range));
+                }
+                for block in region.shared() {
+                    let areas: Vec<String> =
+                        block.areas.iter().map(Area::to_string).collect();
+                    println!(
+                        "    shared: {} by {}",
+                        format_range(&block.range),
+                        areas.join(", ")
+                    );
+                }
+            }
+        }
+        Format::Debug => println!("{:#?}", report),
+        Format::Json | Format::Ron => print_serialized(&report, format)?,
+    }
+
+    Ok(())
+}
+
+/// Apply an IPS or BPS patch and save the patched rom.
+fn apply_patch(rom: &Rom, path: &str, out: &str) -> Result<()> {
+    let bytes =
+        fs::read(path).with_context(|| format!("failed to read {}", path))?;
+
+    // BPS patches start with "BPS1", IPS patches with "PATCH"
+    let (patched, changes) = if bytes.starts_with(b"BPS1") {
+        let patch = BpsPatch::from_bytes(&bytes)
+            .with_context(|| format!("invalid BPS patch: {}", path))?;
+        (rom.apply_bps(&patch)?, format!("{} actions", patch.actions.len()))
+    } else {
+        let patch = IpsPatch::from_bytes(&bytes)
+            .with_context(|| format!("invalid IPS patch: {}", path))?;
+        (rom.apply_ips(&patch)?, format!("{} records", patch.records.len()))
+    };
+    patched.save(out)?;
+    println!("applied {}, saved to {}", changes, out);
+
+    Ok(())
+}
+
+/// Write a patch that turns one rom into another.
+fn create_patch(rom: &Rom, edited: &Rom, out: &str) -> Result<()> {
+    let (bytes, changes) = if out.to_lowercase().ends_with(".bps") {
+        let patch = rom.create_bps(edited);
+        (patch.to_bytes(), format!("{} actions", patch.actions.len()))
+    } else {
+        let patch = rom.create_ips(edited)?;
+        (patch.to_bytes()?, format!("{} records", patch.records.len()))
+    };
+    fs::write(out, &bytes)
+        .with_context(|| format!("failed to write {}", out))?;
+    println!("wrote {} ({} bytes) to {}", changes, bytes.len(), out);
+
+    Ok(())
+}
+
+/// Compare every area (or just one level) between two roms.
+fn diff(
+    rom: &Rom,
+    other: &Rom,
+    level_name: Option<&str>,
+    format: Format,
+) -> Result<()> {
+    // every area, not just the ones a level starts in, so bonus rooms and
+    // the areas behind pipes are compared too
+    let level_names = match level_name {
+        Some(name) => vec![LevelName::parse(name)?],
+        None => rom.areas()?.into_iter().map(LevelName::Area).collect(),
+    };
+
+    let mut diffs = vec![];
+    for level_name in level_names {
+        let level = level_name.get(rom)?;
+        let other_level = level_name.get(other)?;
+        let changes = level.diff(&other_level);
+        if !changes.is_empty() {
+            diffs.push(LevelDiff { level: level_name.to_string(), changes });
+        }
+    }
+
+    match format {
+        Format::Text => {
+            for diff in &diffs {
+                println!("{}:", diff.level);
+                for change in &diff.changes {
+                    println!("    {}", change);
+                }
+            }
+            if diffs.is_empty() {
+                println!("no differences");
+            }
+        }
+        Format::Debug => println!("{:#?}", diffs),
+        Format::Json | Format::Ron => print_serialized(&diffs, format)?,
+    }
+
+    Ok(())
+}
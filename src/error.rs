@@ -0,0 +1,72 @@
+use std::fmt;
+
+use crate::Offset;
+
+pub type ParseResult<T> = std::result::Result<T, ParseError>;
+
+/**
+ * An error found while parsing data from a rom.
+ *
+ * Offsets are relative to the data that was given to the parser, the rom
+ * adjusts them with `offset_by` so they point into the rom file.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The data ended in the middle of a value.
+    UnexpectedEof { offset: Offset },
+    /// The data ended before the terminator byte was found.
+    MissingTerminator { terminator: u8, offset: Offset },
+    /// A value that doesn't map to anything, like an unknown level name.
+    UnknownValue { kind: &'static str, value: String },
+    /// A pointer to a CPU address outside of PRG-ROM.
+    InvalidAddress { address: u16 },
+    /// A CRC32 checksum that doesn't match the data it was made from.
+    ChecksumMismatch { kind: &'static str, expected: u32, actual: u32 },
+}
+
+impl ParseError {
+    pub fn unknown_value<T: fmt::Debug>(kind: &'static str, value: T) -> Self {
+        Self::UnknownValue { kind, value: format!("{:?}", value) }
+    }
+
+    /// Move the offset of the error by the offset the data started at.
+    pub fn offset_by(self, base: Offset) -> Self {
+        match self {
+            Self::UnexpectedEof { offset } => {
+                Self::UnexpectedEof { offset: base + offset }
+            }
+            Self::MissingTerminator { terminator, offset } => {
+                Self::MissingTerminator { terminator, offset: base + offset }
+            }
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof { offset } => {
+                write!(f, "unexpected end of data at offset {:#x}", offset)
+            }
+            Self::MissingTerminator { terminator, offset } => write!(
+                f,
+                "missing {:#04x} terminator, data ends at offset {:#x}",
+                terminator, offset
+            ),
+            Self::UnknownValue { kind, value } => {
+                write!(f, "unknown {}: {}", kind, value)
+            }
+            Self::InvalidAddress { address } => {
+                write!(f, "address ${:04X} is outside of PRG-ROM", address)
+            }
+            Self::ChecksumMismatch { kind, expected, actual } => write!(
+                f,
+                "{} checksum is {:08x}, expected {:08x}",
+                kind, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
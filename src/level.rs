@@ -1,12 +1,31 @@
+mod area;
+mod coordinate;
+mod diff;
 mod enemy_data;
 mod header;
 mod object_data;
+#[cfg(feature = "serde")]
+mod serialize;
+mod validate;
 
+pub use area::*;
+pub use coordinate::*;
+pub use diff::*;
 pub use enemy_data::*;
 pub use header::*;
 pub use object_data::*;
+#[cfg(feature = "serde")]
+pub use serialize::*;
+pub use validate::*;
 
-#[derive(Debug)]
+use std::fmt;
+
+use anyhow::{bail, ensure, Result};
+
+use crate::ParseResult;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Level {
     pub level_header: LevelHeader,
     pub object_data: LevelObjectData,
@@ -21,4 +40,84 @@ impl Level {
     ) -> Self {
         Self { level_header, object_data, enemy_data }
     }
+
+    /**
+     * Decode a level from the area data (the header followed by the object
+     * data) and the enemy data, the opposite of `to_bytes`.
+     */
+    pub fn from_bytes(
+        area_data: &[u8],
+        enemy_data: &[u8],
+    ) -> ParseResult<Self> {
+        let level_header = LevelHeader::from_bytes(area_data)?;
+        let object_data = LevelObjectData::from_bytes(&area_data[2..])
+            .map_err(|err| err.offset_by(2))?;
+        let enemy_data = LevelEnemyData::from_bytes(enemy_data)?;
+
+        Ok(Self { level_header, object_data, enemy_data })
+    }
+
+    /**
+     * Encode the level back into bytes as they are stored in the rom.
+     *
+     * Returns the area data (the header followed by the object data) and the
+     * enemy data, which are stored separately.
+     */
+    pub fn to_bytes(&self) -> (Vec<u8>, Vec<u8>) {
+        let mut area_data = self.level_header.to_bytes().to_vec();
+        area_data.extend(self.object_data.to_bytes());
+
+        let enemy_data = self.enemy_data.to_bytes();
+
+        (area_data, enemy_data)
+    }
+
+    /**
+     * Check that the level can be stored in the rom as-is.
+     *
+     * Most fields are packed into a few bits, so a value that doesn't fit
+     * (like a column past 15) would change when encoded, as would an entry
+     * whose bytes look like a terminator.  Levels built by hand or imported
+     * should be checked before they are written into a rom.
+     */
+    pub fn check_encoding(&self) -> Result<()> {
+        let (area_data, enemy_data) = self.to_bytes();
+        let decoded = Self::from_bytes(&area_data, &enemy_data)?;
+
+        ensure!(
+            decoded.level_header == self.level_header,
+            "level header can't be encoded: {:?}",
+            self.level_header
+        );
+        check_entries(
+            "object",
+            &self.object_data.objects,
+            &decoded.object_data.objects,
+        )?;
+        check_entries(
+            "enemy data entry",
+            &self.enemy_data.entries,
+            &decoded.enemy_data.entries,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Find the first entry that didn't survive encoding.
+fn check_entries<T: PartialEq + fmt::Debug>(
+    name: &str,
+    entries: &[T],
+    decoded: &[T],
+) -> Result<()> {
+    let len = entries.len().max(decoded.len());
+    let Some(idx) = (0..len).find(|&idx| entries.get(idx) != decoded.get(idx))
+    else {
+        return Ok(());
+    };
+
+    match entries.get(idx) {
+        Some(entry) => bail!("{} {} can't be encoded: {:?}", name, idx, entry),
+        None => bail!("level can't be encoded, {} {} was added", name, idx),
+    }
 }
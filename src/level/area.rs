@@ -0,0 +1,84 @@
+use std::fmt;
+
+use crate::util::enum_mapped;
+use crate::{ParseError, ParseResult};
+
+/// An area in the game, referenced by its area type and its index within that
+/// type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Area {
+    pub area_type: AreaType,
+    pub index: u8,
+}
+
+impl Area {
+    pub fn new(area_type: AreaType, index: u8) -> Self {
+        Self { area_type, index }
+    }
+
+    /// Create an area from a name like "ground-5" or "underground-2".
+    pub fn from_name(name: &str) -> ParseResult<Self> {
+        let unknown = || ParseError::unknown_value("area name", name);
+        let (area_type, index) = name.split_once('-').ok_or_else(unknown)?;
+
+        let area_type = match area_type {
+            "water" => AreaType::Water,
+            "ground" => AreaType::Ground,
+            "underground" => AreaType::Underground,
+            "castle" => AreaType::Castle,
+            _ => return Err(unknown()),
+        };
+        let index = index.parse().map_err(|_| unknown())?;
+        if index > 0b00011111 {
+            return Err(unknown());
+        }
+
+        Ok(Self { area_type, index })
+    }
+
+    /**
+     * xTTIIIII
+     *
+     * T = area type, I = area index.  The high bit is not part of the area
+     * pointer and is ignored.
+     */
+    pub fn from_byte(byte: u8) -> Self {
+        let area_type = AreaType::new((byte & 0b01100000) >> 5);
+        let index = byte & 0b00011111;
+
+        Self { area_type, index }
+    }
+
+    /// Get the area pointer byte for this area.
+    pub fn value(&self) -> u8 {
+        self.area_type.value() << 5 | self.index
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let area_type = match self.area_type {
+            AreaType::Water => "water",
+            AreaType::Ground => "ground",
+            AreaType::Underground => "underground",
+            AreaType::Castle => "castle",
+        };
+        write!(f, "{}-{}", area_type, self.index)
+    }
+}
+
+enum_mapped!(
+    pub AreaType (u8) {
+        0b00 => Water,
+        0b01 => Ground,
+        0b10 => Underground,
+        0b11 => Castle,
+    }
+);
+
+impl AreaType {
+    pub fn all() -> [Self; 4] {
+        [Self::Water, Self::Ground, Self::Underground, Self::Castle]
+    }
+}
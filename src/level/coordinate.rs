@@ -0,0 +1,80 @@
+/// A position within a page of a level: a column (0-15) and a row (0-15).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coordinate {
+    pub column: u8,
+    pub row: u8,
+}
+
+impl Coordinate {
+    pub fn new(column: u8, row: u8) -> Self {
+        Self { column, row }
+    }
+
+    /**
+     * XXXXYYYY
+     *
+     * X = column, Y = row.
+     */
+    pub fn from_byte(byte: u8) -> Self {
+        let column = (byte & 0b11110000) >> 4;
+        let row = byte & 0b00001111;
+
+        Self { column, row }
+    }
+
+    pub fn to_byte(&self) -> u8 {
+        (self.column & 0x0f) << 4 | (self.row & 0x0f)
+    }
+
+    /// Get the tile X coordinate from the start of the level, given the page
+    /// this coordinate is on (each page is 16 tiles wide).
+    pub fn absolute_x(&self, page: u8) -> u16 {
+        page as u16 * 16 + self.column as u16
+    }
+}
+
+/// A position within a whole level: the page and the coordinate on that page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LevelPosition {
+    pub page: u8,
+    pub coordinate: Coordinate,
+}
+
+impl LevelPosition {
+    pub fn new(page: u8, coordinate: Coordinate) -> Self {
+        Self { page, coordinate }
+    }
+
+    /// Get the tile X coordinate from the start of the level.
+    pub fn x(&self) -> u16 {
+        self.coordinate.absolute_x(self.page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_byte() {
+        assert_eq!(Coordinate::from_byte(0x07), Coordinate::new(0, 7));
+        assert_eq!(Coordinate::from_byte(0x47), Coordinate::new(4, 7));
+        assert_eq!(Coordinate::from_byte(0xfe), Coordinate::new(15, 14));
+    }
+
+    #[test]
+    fn test_to_byte() {
+        for byte in 0..=255 {
+            assert_eq!(Coordinate::from_byte(byte).to_byte(), byte);
+        }
+    }
+
+    #[test]
+    fn test_absolute_x() {
+        assert_eq!(Coordinate::new(0, 7).absolute_x(0), 0);
+        assert_eq!(Coordinate::new(4, 7).absolute_x(1), 20);
+        assert_eq!(Coordinate::new(15, 0).absolute_x(31), 511);
+    }
+}
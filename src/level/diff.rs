@@ -0,0 +1,365 @@
+use std::fmt;
+
+use crate::{
+    Area, Level, LevelEnemyEntry, LevelEnemyKind, LevelObjectKind,
+    LevelPosition,
+};
+
+/// Something in a level that can be added, removed or moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LevelEntry {
+    Object(LevelObjectKind),
+    Enemy { kind: LevelEnemyKind, hard_mode: bool },
+    PipePointer { area: Area, world: u8, page: u8 },
+}
+
+impl fmt::Display for LevelEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Object(kind) => write!(f, "{:?}", kind),
+            Self::Enemy { kind, hard_mode } => write!(
+                f,
+                "{:?}{}",
+                kind,
+                if *hard_mode { " (hard mode)" } else { "" }
+            ),
+            Self::PipePointer { area, world, page } => write!(
+                f,
+                "pipe pointer to {} page {} (world {})",
+                area,
+                page,
+                world + 1
+            ),
+        }
+    }
+}
+
+/// A difference between two levels, see `Level::diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LevelChange {
+    Header { field: String, old: String, new: String },
+    Added { entry: LevelEntry, position: LevelPosition },
+    Removed { entry: LevelEntry, position: LevelPosition },
+    Moved { entry: LevelEntry, from: LevelPosition, to: LevelPosition },
+}
+
+impl fmt::Display for LevelChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header { field, old, new } => {
+                write!(f, "header: {} changed from {} to {}", field, old, new)
+            }
+            Self::Added { entry, position } => write!(
+                f,
+                "page {}: {} at {} added",
+                position.page,
+                entry,
+                format_coordinate(position)
+            ),
+            Self::Removed { entry, position } => write!(
+                f,
+                "page {}: {} at {} removed",
+                position.page,
+                entry,
+                format_coordinate(position)
+            ),
+            Self::Moved { entry, from, to } => write!(
+                f,
+                "page {}: {} at {} moved to page {} {}",
+                from.page,
+                entry,
+                format_coordinate(from),
+                to.page,
+                format_coordinate(to)
+            ),
+        }
+    }
+}
+
+fn format_coordinate(position: &LevelPosition) -> String {
+    format!(
+        "col {} row {}",
+        position.coordinate.column, position.coordinate.row
+    )
+}
+
+impl Level {
+    /**
+     * Compare two levels by what they contain rather than by their bytes:
+     * header fields that changed, then objects and enemies that were added,
+     * removed or moved.
+     *
+     * Entries are matched by what they are and where they are in the level,
+     * so re-ordering them or moving them between pages with page skips
+     * instead of new page flags doesn't show up.  Page skips themselves
+     * aren't compared.  An entry that isn't where it was is reported as
+     * moved when the other level has the same entry somewhere new.
+     */
+    pub fn diff(&self, other: &Level) -> Vec<LevelChange> {
+        let mut changes = self.diff_header(other);
+        changes.extend(diff_entries(self.objects(), other.objects()));
+        changes.extend(diff_entries(self.enemies(), other.enemies()));
+
+        changes
+    }
+
+    fn diff_header(&self, other: &Level) -> Vec<LevelChange> {
+        let (old, new) = (&self.level_header, &other.level_header);
+        let fields = [
+            ("time", format!("{:?}", old.time), format!("{:?}", new.time)),
+            (
+                "start position",
+                format!("{:?}", old.start_position),
+                format!("{:?}", new.start_position),
+            ),
+            (
+                "start autowalk",
+                old.start_autowalk.to_string(),
+                new.start_autowalk.to_string(),
+            ),
+            (
+                "background",
+                format!("{:?}", old.background),
+                format!("{:?}", new.background),
+            ),
+            (
+                "scenery",
+                format!("{:?}", old.scenery),
+                format!("{:?}", new.scenery),
+            ),
+            (
+                "platform",
+                format!("{:?}", old.platform),
+                format!("{:?}", new.platform),
+            ),
+            (
+                "ground",
+                format!("{:?}", old.ground),
+                format!("{:?}", new.ground),
+            ),
+        ];
+
+        fields
+            .into_iter()
+            .filter(|(_, old, new)| old != new)
+            .map(|(field, old, new)| LevelChange::Header {
+                field: field.to_string(),
+                old,
+                new,
+            })
+            .collect()
+    }
+
+    fn objects(&self) -> Vec<(LevelEntry, LevelPosition)> {
+        self.object_data
+            .objects
+            .iter()
+            .zip(self.object_data.positions())
+            .filter(|(object, _)| {
+                !matches!(object.kind, LevelObjectKind::PageSkip(_))
+            })
+            .map(|(object, position)| {
+                (LevelEntry::Object(object.kind.clone()), position)
+            })
+            .collect()
+    }
+
+    fn enemies(&self) -> Vec<(LevelEntry, LevelPosition)> {
+        self.enemy_data
+            .entries
+            .iter()
+            .zip(self.enemy_data.positions())
+            .filter_map(|(entry, position)| {
+                let entry = match entry {
+                    LevelEnemyEntry::Enemy(enemy) => {
+                        if let LevelEnemyKind::PageSkip(_) = enemy.kind {
+                            return None;
+                        }
+                        LevelEntry::Enemy {
+                            kind: enemy.kind.clone(),
+                            hard_mode: enemy.hard_mode_flag,
+                        }
+                    }
+                    LevelEnemyEntry::PipePointer(pipe_pointer) => {
+                        LevelEntry::PipePointer {
+                            area: pipe_pointer.area,
+                            world: pipe_pointer.world,
+                            page: pipe_pointer.page,
+                        }
+                    }
+                };
+
+                Some((entry, position))
+            })
+            .collect()
+    }
+}
+
+/// Match up the entries of two levels, in the order of the old level with
+/// whatever was added after.
+fn diff_entries(
+    old: Vec<(LevelEntry, LevelPosition)>,
+    new: Vec<(LevelEntry, LevelPosition)>,
+) -> Vec<LevelChange> {
+    // entries that didn't change
+    let mut new: Vec<Option<(LevelEntry, LevelPosition)>> =
+        new.into_iter().map(Some).collect();
+    let mut removed = vec![];
+    for (entry, position) in old {
+        match new.iter_mut().find(|other| {
+            matches!(other, Some((other_entry, other_position))
+                if *other_entry == entry && *other_position == position)
+        }) {
+            Some(other) => *other = None,
+            None => removed.push((entry, position)),
+        }
+    }
+
+    let mut changes = vec![];
+    for (entry, position) in removed {
+        match new.iter_mut().find(|other| {
+            matches!(other, Some((other_entry, _)) if *other_entry == entry)
+        }) {
+            Some(other) => {
+                let (_, to) = other.take().unwrap();
+                changes.push(LevelChange::Moved { entry, from: position, to });
+            }
+            None => changes.push(LevelChange::Removed { entry, position }),
+        }
+    }
+    changes.extend(
+        new.into_iter()
+            .flatten()
+            .map(|(entry, position)| LevelChange::Added { entry, position }),
+    );
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AreaType, Coordinate};
+
+    fn level(area_data: &[u8], enemy_data: &[u8]) -> Level {
+        Level::from_bytes(area_data, enemy_data).unwrap()
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let a = level(
+            &[0x50, 0x21, 0x47, 0x21, 0x27, 0x01, 0xfd],
+            &[0xba, 0x06, 0xff],
+        );
+        // the same objects in a different order
+        let b = level(
+            &[0x50, 0x21, 0x27, 0x01, 0x47, 0x21, 0xfd],
+            &[0xba, 0x06, 0xff],
+        );
+
+        assert_eq!(a.diff(&a), vec![]);
+        assert_eq!(a.diff(&b), vec![]);
+
+        // a new page flag and a page skip to the same page
+        let a = level(&[0x50, 0x21, 0x47, 0xa1, 0xfd], &[0xff]);
+        let b = level(&[0x50, 0x21, 0x0d, 0x01, 0x47, 0x21, 0xfd], &[0xff]);
+        assert_eq!(a.diff(&b), vec![]);
+    }
+
+    #[test]
+    fn test_changes() {
+        let a = level(
+            &[
+                0x50, 0x21, // header
+                0x47, 0x24, // 5 bricks
+                0x27, 0x01, // ? block (coin)
+                0xfd,
+            ],
+            &[
+                0xba, 0x06, // goomba
+                0x1e, 0xc2, 0x00, // pipe pointer, new page
+                0xff,
+            ],
+        );
+        let b = level(
+            &[
+                0x90, 0x21, // time 300
+                0x27, 0x01, // ? block (coin)
+                0x57, 0x01, // ? block (coin)
+                0x48, 0xa4, // 5 bricks, new page
+                0xfd,
+            ],
+            &[
+                0xba, 0x46, // goomba (hard mode)
+                0xff,
+            ],
+        );
+
+        let changes = a.diff(&b);
+        let pos = |page, column, row| {
+            LevelPosition::new(page, Coordinate::new(column, row))
+        };
+        assert_eq!(
+            changes,
+            vec![
+                LevelChange::Header {
+                    field: "time".to_string(),
+                    old: "T400".to_string(),
+                    new: "T300".to_string(),
+                },
+                LevelChange::Moved {
+                    entry: LevelEntry::Object(
+                        LevelObjectKind::HorizontalBrick(5)
+                    ),
+                    from: pos(0, 4, 7),
+                    to: pos(1, 4, 8),
+                },
+                LevelChange::Added {
+                    entry: LevelEntry::Object(
+                        LevelObjectKind::QuestionBlockCoin
+                    ),
+                    position: pos(0, 5, 7),
+                },
+                LevelChange::Removed {
+                    entry: LevelEntry::Enemy {
+                        kind: LevelEnemyKind::Goomba,
+                        hard_mode: false,
+                    },
+                    position: pos(0, 11, 10),
+                },
+                LevelChange::Removed {
+                    entry: LevelEntry::PipePointer {
+                        area: Area::new(AreaType::Underground, 2),
+                        world: 0,
+                        page: 0,
+                    },
+                    position: pos(1, 1, 14),
+                },
+                LevelChange::Added {
+                    entry: LevelEntry::Enemy {
+                        kind: LevelEnemyKind::Goomba,
+                        hard_mode: true,
+                    },
+                    position: pos(0, 11, 10),
+                },
+            ]
+        );
+
+        assert_eq!(
+            changes[1].to_string(),
+            "page 0: HorizontalBrick(5) at col 4 row 7 moved to page 1 col 4 \
+             row 8"
+        );
+        assert_eq!(
+            changes[2].to_string(),
+            "page 0: QuestionBlockCoin at col 5 row 7 added"
+        );
+        assert_eq!(
+            changes[4].to_string(),
+            "page 1: pipe pointer to underground-2 page 0 (world 1) at col 1 \
+             row 14 removed"
+        );
+    }
+}
@@ -1,18 +1,33 @@
-#[derive(Debug)]
+use crate::{Area, Coordinate, LevelPosition, ParseError, ParseResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LevelEnemyData {
-    pub enemies: Vec<LevelEnemy>,
-    pub pipe_pointers: Vec<PipePointer>,
+    pub entries: Vec<LevelEnemyEntry>,
+}
+
+/// Enemy data interleaves enemies and pipe pointers, their order matters for
+/// page tracking so they are kept in a single list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LevelEnemyEntry {
+    Enemy(LevelEnemy),
+    PipePointer(PipePointer),
 }
 
 impl LevelEnemyData {
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let mut enemies = vec![];
-        let mut pipe_pointers = vec![];
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        let mut entries = vec![];
 
         // process byte-by-byte
         let mut idx = 0;
         loop {
-            let byte = bytes[idx];
+            let Some(&byte) = bytes.get(idx) else {
+                return Err(ParseError::MissingTerminator {
+                    terminator: 0xFF,
+                    offset: idx,
+                });
+            };
 
             // 0xFF is the end enemy marker
             if byte == 0xFF {
@@ -21,98 +36,404 @@ impl LevelEnemyData {
 
             if (byte & 0x0F) == 0x0E {
                 // pipe pointer (3 bytes)
-                pipe_pointers.push(PipePointer::from_bytes(&bytes[idx..]));
+                let pipe_pointer = PipePointer::from_bytes(&bytes[idx..])
+                    .map_err(|err| err.offset_by(idx))?;
+                entries.push(LevelEnemyEntry::PipePointer(pipe_pointer));
                 idx += 3;
             } else {
                 // enemy pointer (2 bytes)
-                enemies.push(LevelEnemy::from_bytes(&bytes[idx..]));
+                let enemy = LevelEnemy::from_bytes(&bytes[idx..])
+                    .map_err(|err| err.offset_by(idx))?;
+                entries.push(LevelEnemyEntry::Enemy(enemy));
                 idx += 2;
             };
         }
 
-        Self { enemies, pipe_pointers }
+        Ok(Self { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for entry in &self.entries {
+            match entry {
+                LevelEnemyEntry::Enemy(enemy) => bytes.extend(enemy.to_bytes()),
+                LevelEnemyEntry::PipePointer(pipe_pointer) => {
+                    bytes.extend(pipe_pointer.to_bytes())
+                }
+            }
+        }
+
+        // 0xFF is the end enemy marker
+        bytes.push(0xFF);
+
+        bytes
+    }
+
+    /**
+     * Get the position of every entry (enemy or pipe pointer) within the
+     * level.
+     *
+     * Like objects, the page is found by walking the entries in order: the
+     * new page flag moves to the next page and a page skip jumps straight to
+     * the page it names.
+     */
+    pub fn positions(&self) -> Vec<LevelPosition> {
+        let mut page: u8 = 0;
+
+        self.entries
+            .iter()
+            .map(|entry| {
+                let (coordinate, new_page_flag) = match entry {
+                    LevelEnemyEntry::Enemy(enemy) => {
+                        (enemy.coordinate, enemy.new_page_flag)
+                    }
+                    LevelEnemyEntry::PipePointer(pipe_pointer) => {
+                        (pipe_pointer.coordinate, pipe_pointer.new_page_flag)
+                    }
+                };
+
+                if new_page_flag {
+                    page = page.wrapping_add(1);
+                }
+                if let LevelEnemyEntry::Enemy(LevelEnemy {
+                    kind: LevelEnemyKind::PageSkip(skip),
+                    ..
+                }) = entry
+                {
+                    page = *skip;
+                }
+
+                LevelPosition::new(page, coordinate)
+            })
+            .collect()
+    }
+
+    pub fn enemies(&self) -> impl Iterator<Item = &LevelEnemy> {
+        self.entries.iter().filter_map(|entry| match entry {
+            LevelEnemyEntry::Enemy(enemy) => Some(enemy),
+            LevelEnemyEntry::PipePointer(_) => None,
+        })
+    }
+
+    pub fn pipe_pointers(&self) -> impl Iterator<Item = &PipePointer> {
+        self.entries.iter().filter_map(|entry| match entry {
+            LevelEnemyEntry::Enemy(_) => None,
+            LevelEnemyEntry::PipePointer(pipe_pointer) => Some(pipe_pointer),
+        })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LevelEnemyKind {
-    QuestionBlockPowerup,
-    QuestionBlockCoin,
-    HiddenBlockCoin,
-    HiddenBlockExtraLife,
-    BrickPowerup,
-    BrickVine,
-    BrickStar,
-    BrickMultiCoinBlock,
-    BrickExtraLife,
-    SidewaysPipe,
-    UsedBlock,
-    Spring,
-    ReverseLPipe,
-    FlagPole,
-    CastleBridge,
-    Nothing,
-    IslandOrCannon,
-    HorizontalBrick,
-    HorizontalBlock,
-    HorizontalCoins,
-    VerticalBrick,
-    VerticalBlock,
-    PipeNoEntry,
-    PipeEntry,
-    Hole,
-    BalanceHorizontalRope,
-    BridgeV7,
-    BridgeV8,
-    BridgeV10,
-    FilledHole,
-    HorizontalQuestionBlockV3,
-    HorizontalQuestionBlockV7,
-    PageSkip,
-    CastleAxe,
-    AxeRope,
-    ScrollStop,
+    GreenKoopaTroopa,
+    RedKoopaTroopaWalksOffFloors,
+    BuzzyBeetle,
+    RedKoopaTroopa,
+    GreenKoopaTroopaStationary,
+    HammerBrother,
+    Goomba,
+    Blooper,
+    BulletBill,
+    YellowKoopaParatroopa,
+    GreenCheepCheep,
     RedCheepCheep,
-    ContinuousBulletBillsOrCheepCheeps,
-    StopContinuation,
-    LoopCommand,
-    DoNotUse,
+    Podoboo,
+    PiranhaPlant,
+    GreenKoopaParatroopaLeaping,
+    RedKoopaParatroopaVertical,
+    GreenKoopaParatroopaHorizontal,
+    Lakitu,
+    Spiny,
+    FlyingCheepCheepGenerator,
+    BowserFireGenerator,
+    FireworksGenerator,
+    BulletBillOrCheepCheepGenerator,
+    StopGenerator,
+    FireBarClockwise,
+    FastFireBarClockwise,
+    FireBarCounterClockwise,
+    FastFireBarCounterClockwise,
+    LongFireBarClockwise,
+    BalanceLift,
+    LiftUpAndDown,
+    LiftUp,
+    LiftDown,
+    LiftLeftAndRight,
+    LiftFalling,
+    LiftRight,
+    ShortLiftUp,
+    ShortLiftDown,
+    Bowser,
+    WarpZone,
+    ToadOrPrincess,
+    TwoGoombasY10,
+    ThreeGoombasY10,
+    TwoGoombasY6,
+    ThreeGoombasY6,
+    TwoGreenKoopaTroopasY10,
+    ThreeGreenKoopaTroopasY10,
+    TwoGreenKoopaTroopasY6,
+    ThreeGreenKoopaTroopasY6,
+    PageSkip(u8), // page
+    Invalid(u8),  // enemy id
 }
 
-#[derive(Debug)]
+impl LevelEnemyKind {
+    pub fn new(y_coordinate: u8, byte: u8) -> Self {
+        match (y_coordinate, byte) {
+            // Y offset 0xf is a page skip, not an enemy
+            (0xf, _) => Self::PageSkip(byte),
+
+            (_, 0x00) => Self::GreenKoopaTroopa,
+            (_, 0x01) => Self::RedKoopaTroopaWalksOffFloors,
+            (_, 0x02) => Self::BuzzyBeetle,
+            (_, 0x03) => Self::RedKoopaTroopa,
+            (_, 0x04) => Self::GreenKoopaTroopaStationary,
+            (_, 0x05) => Self::HammerBrother,
+            (_, 0x06) => Self::Goomba,
+            (_, 0x07) => Self::Blooper,
+            (_, 0x08) => Self::BulletBill,
+            (_, 0x09) => Self::YellowKoopaParatroopa,
+            (_, 0x0a) => Self::GreenCheepCheep,
+            (_, 0x0b) => Self::RedCheepCheep,
+            (_, 0x0c) => Self::Podoboo,
+            (_, 0x0d) => Self::PiranhaPlant,
+            (_, 0x0e) => Self::GreenKoopaParatroopaLeaping,
+            (_, 0x0f) => Self::RedKoopaParatroopaVertical,
+            (_, 0x10) => Self::GreenKoopaParatroopaHorizontal,
+            (_, 0x11) => Self::Lakitu,
+            // only spawned by lakitu, not intended to be placed directly
+            (_, 0x12) => Self::Spiny,
+
+            // generators
+            (_, 0x14) => Self::FlyingCheepCheepGenerator,
+            (_, 0x15) => Self::BowserFireGenerator,
+            (_, 0x16) => Self::FireworksGenerator,
+            (_, 0x17) => Self::BulletBillOrCheepCheepGenerator,
+            (_, 0x18) => Self::StopGenerator,
+
+            // fire bars
+            (_, 0x1b) => Self::FireBarClockwise,
+            (_, 0x1c) => Self::FastFireBarClockwise,
+            (_, 0x1d) => Self::FireBarCounterClockwise,
+            (_, 0x1e) => Self::FastFireBarCounterClockwise,
+            (_, 0x1f) => Self::LongFireBarClockwise,
+
+            // lifts
+            (_, 0x24) => Self::BalanceLift,
+            (_, 0x25) => Self::LiftUpAndDown,
+            (_, 0x26) => Self::LiftUp,
+            (_, 0x27) => Self::LiftDown,
+            (_, 0x28) => Self::LiftLeftAndRight,
+            (_, 0x29) => Self::LiftFalling,
+            (_, 0x2a) => Self::LiftRight,
+            (_, 0x2b) => Self::ShortLiftUp,
+            (_, 0x2c) => Self::ShortLiftDown,
+
+            (_, 0x2d) => Self::Bowser,
+            (_, 0x34) => Self::WarpZone,
+            (_, 0x35) => Self::ToadOrPrincess,
+
+            // enemy groups (Y coordinate is ignored)
+            (_, 0x37) => Self::TwoGoombasY10,
+            (_, 0x38) => Self::ThreeGoombasY10,
+            (_, 0x39) => Self::TwoGoombasY6,
+            (_, 0x3a) => Self::ThreeGoombasY6,
+            (_, 0x3b) => Self::TwoGreenKoopaTroopasY10,
+            (_, 0x3c) => Self::ThreeGreenKoopaTroopasY10,
+            (_, 0x3d) => Self::TwoGreenKoopaTroopasY6,
+            (_, 0x3e) => Self::ThreeGreenKoopaTroopasY6,
+
+            // unused or not meant to be placed in level data
+            (_, _) => Self::Invalid(byte),
+        }
+    }
+
+    /// Get the enemy id (or page number for page skips) for this enemy kind.
+    pub fn value(&self) -> u8 {
+        match self {
+            Self::PageSkip(page) => *page,
+            Self::GreenKoopaTroopa => 0x00,
+            Self::RedKoopaTroopaWalksOffFloors => 0x01,
+            Self::BuzzyBeetle => 0x02,
+            Self::RedKoopaTroopa => 0x03,
+            Self::GreenKoopaTroopaStationary => 0x04,
+            Self::HammerBrother => 0x05,
+            Self::Goomba => 0x06,
+            Self::Blooper => 0x07,
+            Self::BulletBill => 0x08,
+            Self::YellowKoopaParatroopa => 0x09,
+            Self::GreenCheepCheep => 0x0a,
+            Self::RedCheepCheep => 0x0b,
+            Self::Podoboo => 0x0c,
+            Self::PiranhaPlant => 0x0d,
+            Self::GreenKoopaParatroopaLeaping => 0x0e,
+            Self::RedKoopaParatroopaVertical => 0x0f,
+            Self::GreenKoopaParatroopaHorizontal => 0x10,
+            Self::Lakitu => 0x11,
+            Self::Spiny => 0x12,
+            Self::FlyingCheepCheepGenerator => 0x14,
+            Self::BowserFireGenerator => 0x15,
+            Self::FireworksGenerator => 0x16,
+            Self::BulletBillOrCheepCheepGenerator => 0x17,
+            Self::StopGenerator => 0x18,
+            Self::FireBarClockwise => 0x1b,
+            Self::FastFireBarClockwise => 0x1c,
+            Self::FireBarCounterClockwise => 0x1d,
+            Self::FastFireBarCounterClockwise => 0x1e,
+            Self::LongFireBarClockwise => 0x1f,
+            Self::BalanceLift => 0x24,
+            Self::LiftUpAndDown => 0x25,
+            Self::LiftUp => 0x26,
+            Self::LiftDown => 0x27,
+            Self::LiftLeftAndRight => 0x28,
+            Self::LiftFalling => 0x29,
+            Self::LiftRight => 0x2a,
+            Self::ShortLiftUp => 0x2b,
+            Self::ShortLiftDown => 0x2c,
+            Self::Bowser => 0x2d,
+            Self::WarpZone => 0x34,
+            Self::ToadOrPrincess => 0x35,
+            Self::TwoGoombasY10 => 0x37,
+            Self::ThreeGoombasY10 => 0x38,
+            Self::TwoGoombasY6 => 0x39,
+            Self::ThreeGoombasY6 => 0x3a,
+            Self::TwoGreenKoopaTroopasY10 => 0x3b,
+            Self::ThreeGreenKoopaTroopasY10 => 0x3c,
+            Self::TwoGreenKoopaTroopasY6 => 0x3d,
+            Self::ThreeGreenKoopaTroopasY6 => 0x3e,
+            Self::Invalid(byte) => *byte,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LevelEnemy {
     pub kind: LevelEnemyKind,
-    pub x_coordinate: u8,
-    pub y_coordinate: u8,
+    pub coordinate: Coordinate,
     pub new_page_flag: bool,
+    pub hard_mode_flag: bool,
 }
 
 impl LevelEnemy {
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        assert!(bytes.len() >= 2);
-        let x_coordinate = bytes[0] << 4;
-        let y_coordinate = bytes[0] & 0b00001111;
-        let kind = LevelEnemyKind::CastleAxe;
+    /**
+     * XXXXYYYY PHOOOOOO
+     *
+     * P = new page flag, H = only appears in hard mode (world 5 and
+     * onwards, or the second quest), O = enemy id (or page number if Y is
+     * 0xf).
+     */
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < 2 {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let coordinate = Coordinate::from_byte(bytes[0]);
         let new_page_flag = bytes[1] & 0b10000000 != 0;
+        let hard_mode_flag = bytes[1] & 0b01000000 != 0;
+        let kind = Self::parse_enemy_kind(bytes);
+
+        Ok(Self { kind, coordinate, new_page_flag, hard_mode_flag })
+    }
+
+    /**
+     * XXXXYYYY PHOOOOOO
+     */
+    pub fn to_bytes(&self) -> [u8; 2] {
+        let byte0 = self.coordinate.to_byte();
+        let byte1 = (self.new_page_flag as u8) << 7
+            | (self.hard_mode_flag as u8) << 6
+            | self.kind.value();
+
+        [byte0, byte1]
+    }
 
-        Self { kind, x_coordinate, y_coordinate, new_page_flag }
+    fn parse_enemy_kind(bytes: &[u8]) -> LevelEnemyKind {
+        let row = Coordinate::from_byte(bytes[0]).row;
+        let byte = bytes[1] & 0b00111111;
+        LevelEnemyKind::new(row, byte)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PipePointer {
-    pub x_coordinate: u8,
-    pub y_coordinate: u8,
+    pub coordinate: Coordinate,
     pub new_page_flag: bool,
+    pub area: Area,
+    pub world: u8,
+    pub page: u8,
 }
 
 impl PipePointer {
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        assert!(bytes.len() >= 3);
-        let x_coordinate = bytes[0] << 4;
-        let y_coordinate = bytes[0] & 0b00001111;
+    /**
+     * XXXXYYYY PAAAAAAA WWWEEEEE
+     *
+     * P = new page flag, A = destination area pointer, W = world the pointer
+     * is active in (0-based, the pointer is ignored in any other world),
+     * E = page to enter the destination area at.
+     */
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < 3 {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let coordinate = Coordinate::from_byte(bytes[0]);
         let new_page_flag = bytes[1] & 0b10000000 != 0;
+        let area = Area::from_byte(bytes[1]);
+        let world = (bytes[2] & 0b11100000) >> 5;
+        let page = bytes[2] & 0b00011111;
+
+        Ok(Self { coordinate, new_page_flag, area, world, page })
+    }
+
+    /**
+     * XXXXYYYY PAAAAAAA WWWEEEEE
+     */
+    pub fn to_bytes(&self) -> [u8; 3] {
+        let byte0 = self.coordinate.to_byte();
+        let byte1 = (self.new_page_flag as u8) << 7 | self.area.value();
+        let byte2 = self.world << 5 | self.page;
+
+        [byte0, byte1, byte2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let bytes = [
+            0x1e, 0xc2, 0x00, // pipe pointer, new page
+            0x6b, 0x06, // goomba on the same page
+            0x0f, 0x05, // page skip to page 5
+            0x8b, 0x86, // goomba, new page
+            0xff,
+        ];
+        let enemy_data = LevelEnemyData::from_bytes(&bytes).unwrap();
+
+        let pages: Vec<u8> =
+            enemy_data.positions().iter().map(|pos| pos.page).collect();
+        assert_eq!(pages, [1, 1, 5, 6]);
+
+        let xs: Vec<u16> =
+            enemy_data.positions().iter().map(|pos| pos.x()).collect();
+        assert_eq!(xs, [17, 22, 80, 104]);
+    }
 
-        Self { x_coordinate, y_coordinate, new_page_flag }
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            LevelEnemyData::from_bytes(&[0x6b, 0x06, 0x1e, 0xc2]).unwrap_err(),
+            ParseError::UnexpectedEof { offset: 4 }
+        );
+        assert_eq!(
+            LevelEnemyData::from_bytes(&[0x6b, 0x06]).unwrap_err(),
+            ParseError::MissingTerminator { terminator: 0xFF, offset: 2 }
+        );
     }
 }
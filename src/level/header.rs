@@ -1,6 +1,8 @@
 use crate::util::enum_mapped;
+use crate::{ParseError, ParseResult};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LevelHeader {
     //pub header_data: &'a [u8],
     pub time: LevelTime,
@@ -41,8 +43,10 @@ impl LevelHeader {
      *                                   |                   |
      *  =====================================================|
      */
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        assert!(bytes.len() >= 2);
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < 2 {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
 
         // first byte
         let time = Self::parse_level_time(bytes);
@@ -55,7 +59,7 @@ impl LevelHeader {
         let platform = Self::parse_level_platform(bytes);
         let ground = Self::parse_level_ground(bytes);
 
-        Self {
+        Ok(Self {
             time,
             start_position,
             start_autowalk,
@@ -63,7 +67,22 @@ impl LevelHeader {
             scenery,
             platform,
             ground,
-        }
+        })
+    }
+
+    /**
+     * TTAYYBBB SSPPGGGG
+     */
+    pub fn to_bytes(&self) -> [u8; 2] {
+        let byte0 = self.time.value() << 6
+            | (self.start_autowalk as u8) << 5
+            | self.start_position.value() << 3
+            | self.background.value();
+        let byte1 = self.platform.value() << 6
+            | self.scenery.value() << 4
+            | self.ground.value();
+
+        [byte0, byte1]
     }
 
     /// TTxxxxxx xxxxxxxx
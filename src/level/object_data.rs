@@ -1,37 +1,82 @@
-#[derive(Debug)]
+use crate::util::enum_mapped;
+use crate::{Coordinate, LevelPosition, ParseError, ParseResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LevelObjectData {
     pub objects: Vec<LevelObject>,
 }
 
 impl LevelObjectData {
-    pub fn from_bytes(bytes: &[u8]) -> Self {
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
         let mut objects = vec![];
 
         // process byte-by-byte
         let mut idx = 0;
         loop {
-            let byte = bytes[idx];
+            let Some(&byte) = bytes.get(idx) else {
+                return Err(ParseError::MissingTerminator {
+                    terminator: 0xFD,
+                    offset: idx,
+                });
+            };
 
             // 0xFD is the end level marker
             if byte == 0xFD {
                 break;
             }
 
-            let object = LevelObject::from_bytes(&bytes[idx..]);
+            let object = LevelObject::from_bytes(&bytes[idx..])
+                .map_err(|err| err.offset_by(idx))?;
 
             objects.push(object);
             idx += 2;
         }
 
-        Self { objects }
+        Ok(Self { objects })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> =
+            self.objects.iter().flat_map(|object| object.to_bytes()).collect();
+
+        // 0xFD is the end level marker
+        bytes.push(0xFD);
+
+        bytes
+    }
+
+    /**
+     * Get the position of every object within the level.
+     *
+     * Objects only store their coordinate within a page, the page is found
+     * by walking the objects in order: the new page flag moves to the next
+     * page and a page skip object jumps straight to the page it names.
+     */
+    pub fn positions(&self) -> Vec<LevelPosition> {
+        let mut page: u8 = 0;
+
+        self.objects
+            .iter()
+            .map(|object| {
+                if object.new_page_flag {
+                    page = page.wrapping_add(1);
+                }
+                if let LevelObjectKind::PageSkip(byte) = object.kind {
+                    page = byte & 0b00011111;
+                }
+
+                LevelPosition::new(page, object.coordinate)
+            })
+            .collect()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LevelObject {
     pub kind: LevelObjectKind,
-    pub x_coordinate: u8,
-    pub y_coordinate: u8,
+    pub coordinate: Coordinate,
     pub new_page_flag: bool,
 }
 
@@ -39,44 +84,59 @@ impl LevelObject {
     /**
      * XXXXYYYY POOOOOOO
      */
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        assert!(bytes.len() >= 2);
-        let x_coordinate = bytes[0] << 4;
-        let y_coordinate = bytes[0] & 0b00001111;
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < 2 {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let coordinate = Coordinate::from_byte(bytes[0]);
         let new_page_flag = bytes[1] & 0b10000000 != 0;
         let kind = Self::parse_object_kind(bytes);
 
-        Self { kind, x_coordinate, y_coordinate, new_page_flag }
+        Ok(Self { kind, coordinate, new_page_flag })
+    }
+
+    /**
+     * XXXXYYYY POOOOOOO
+     */
+    pub fn to_bytes(&self) -> [u8; 2] {
+        let byte0 = self.coordinate.to_byte();
+        let byte1 = (self.new_page_flag as u8) << 7 | self.kind.value();
+
+        [byte0, byte1]
     }
 
     fn parse_object_kind(bytes: &[u8]) -> LevelObjectKind {
-        let y_coordinate = bytes[0] & 0b00001111;
+        let row = Coordinate::from_byte(bytes[0]).row;
         let byte = bytes[1] & 0b01111111;
-        LevelObjectKind::new(y_coordinate, byte)
+        LevelObjectKind::new(row, byte)
     }
 }
 
-#[derive(Debug)]
-pub enum SceneryKind {
-    Nothing,
-    Clouds,
-    Mountains,
-    Fences,
-}
+enum_mapped!(
+    pub SceneryKind (u8) {
+        0b00 => Nothing,
+        0b01 => Clouds,
+        0b10 => Mountains,
+        0b11 => Fences,
+    }
+);
 
-#[derive(Debug)]
-pub enum ChangeBackgroundKind {
-    Nothing,
-    InWater,
-    CastleWall,
-    OverWater,
-    Night,
-    Snow,
-    NightAndSnow,
-    NightAndCastle,
-}
+enum_mapped!(
+    pub ChangeBackgroundKind (u8) {
+        0b000 => Nothing,
+        0b001 => InWater,
+        0b010 => CastleWall,
+        0b011 => OverWater,
+        0b100 => Night,
+        0b101 => Snow,
+        0b110 => NightAndSnow,
+        0b111 => NightAndCastle,
+    }
+);
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LevelObjectKind {
     QuestionBlockPowerup,
     QuestionBlockCoin,
@@ -114,12 +174,13 @@ pub enum LevelObjectKind {
     CastleAxe,
     AxeRope,
     ScrollStop,
+    ScrollStopAlternate,
     ScrollStopWarpZone,
     RedCheepCheep,
     ContinuousBulletBillsOrCheepCheeps,
     StopContinuation,
     LoopCommand,
-    Invalid,
+    Invalid(u8), // byte
 
     LayoutEmpty(SceneryKind),
     LayoutFloor1Mddle0Ceiling0(SceneryKind),
@@ -142,6 +203,7 @@ pub enum LevelObjectKind {
     LiftRope,
     BalanceLiftVerticalRope(u8), // length
     BigCastle,
+    Castle(u8), // starting row
     Staircase(u8), // width
     TallReverseLPipe(u8), // y
 }
@@ -165,7 +227,7 @@ impl LevelObjectKind {
             (0x0..=0xb, 0x09) => Self::SidewaysPipe,
             (0x0..=0xb, 0x0a) => Self::UsedBlock,
             (0x0..=0xb, 0x0b) => Self::Spring,
-            (0x0..=0xb, 0x0c..=0x0f) => Self::Invalid,
+            (0x0..=0xb, 0x0c..=0x0f) => Self::Invalid(byte),
 
             (0x0..=0xb, 0x10..=0x1f) => Self::IslandOrCannon(low_nibble + 1),
             (0x0..=0xb, 0x20..=0x2f) => Self::HorizontalBrick(low_nibble + 1),
@@ -174,10 +236,10 @@ impl LevelObjectKind {
 
             // anything above 12 is invalid (screen max)
             (0x0..=0xb, 0x50..=0x5b) => Self::VerticalBrick(low_nibble + 1),
-            (0x0..=0xb, 0x5c..=0x5f) => Self::Invalid,
+            (0x0..=0xb, 0x5c..=0x5f) => Self::Invalid(byte),
 
             (0x0..=0xb, 0x60..=0x6b) => Self::VerticalBlock(low_nibble + 1),
-            (0x0..=0xb, 0x6c..=0x6f) => Self::Invalid,
+            (0x0..=0xb, 0x6c..=0x6f) => Self::Invalid(byte),
 
             (0x0..=0xb, 0x70..=0x77) => Self::PipeNoEntry(low_nibble + 2),
             (0x0..=0xb, 0x78..=0x7f) => Self::PipeEntry(low_nibble - 6),
@@ -205,13 +267,13 @@ impl LevelObjectKind {
             (0xd, 0x44) => Self::CastleBridge,
             (0xd, 0x45) => Self::ScrollStopWarpZone,
             (0xd, 0x46) => Self::ScrollStop,
-            (0xd, 0x47) => Self::ScrollStop,
+            (0xd, 0x47) => Self::ScrollStopAlternate,
             (0xd, 0x48) => Self::RedCheepCheep,
             (0xd, 0x49) => Self::ContinuousBulletBillsOrCheepCheeps,
             (0xd, 0x4a) => Self::StopContinuation,
             (0xd, 0x4b) => Self::LoopCommand,
-            (0xd, 0x4c..=0x4f) => Self::Invalid,
-            (0xd, 0x50..=0x7f) => Self::Invalid,
+            (0xd, 0x4c..=0x4f) => Self::Invalid(byte),
+            (0xd, 0x50..=0x7f) => Self::Invalid(byte),
 
             // Y offset 0xe
             (0xe, 0x00) => Self::LayoutEmpty(SceneryKind::Nothing),
@@ -402,28 +464,215 @@ impl LevelObjectKind {
             (0xe, 0x45) => Self::ChangeBackground(ChangeBackgroundKind::Snow),
             (0xe, 0x46) => Self::ChangeBackground(ChangeBackgroundKind::NightAndSnow),
             (0xe, 0x47) => Self::ChangeBackground(ChangeBackgroundKind::NightAndCastle),
-            (0xe, 0x48..=0x7f) => {
-                Self::Invalid
-            }
+            (0xe, 0x48..=0x7f) => Self::Invalid(byte),
 
             // Y offset 0xf
             (0xf, 0x00) => Self::LiftRope,
-            (0xf, 0x01..=0x0f) => Self::Invalid,
+            (0xf, 0x01..=0x0f) => Self::Invalid(byte),
             (0xf, 0x10..=0x1f) => Self::BalanceLiftVerticalRope(low_nibble + 1),
             (0xf, 0x20) => Self::BigCastle,
-            (0xf, 0x21..=0x2f) => Self::Invalid,
+            (0xf, 0x21..=0x2f) => Self::Castle(low_nibble),
             (0xf, 0x30..=0x38) => Self::Staircase(low_nibble + 1),
-            (0xf, 0x39..=0x3f) => Self::Invalid,
-            (0xf, 0x40..=0x42) => Self::Invalid,
+            (0xf, 0x39..=0x3f) => Self::Invalid(byte),
+            (0xf, 0x40..=0x42) => Self::Invalid(byte),
             (0xf, 0x43..=0x4a) => Self::TallReverseLPipe(low_nibble),
-            (0xf, 0x4b..=0x4f) => Self::Invalid,
-            (0xf, 0x50..=0x5f) => Self::Invalid,
+            (0xf, 0x4b..=0x4f) => Self::Invalid(byte),
+            (0xf, 0x50..=0x5f) => Self::Invalid(byte),
             (0xf, 0x60) => Self::Nothing,
-            (0xf, 0x61..=0x6f) => Self::Invalid,
-            (0xf, 0x70..=0x7f) => Self::Invalid,
+            (0xf, 0x61..=0x6f) => Self::Invalid(byte),
+            (0xf, 0x70..=0x7f) => Self::Invalid(byte),
 
-            _ => Self::Invalid,
+            _ => Self::Invalid(byte),
             //_ => unreachable!("invalid level object byte: ({}, {})", y_coordinate, byte),
         }
     }
+
+    /**
+     * Get the object byte (without the new page flag) for this object kind.
+     *
+     * The row an object is placed at is also needed to tell some kinds apart,
+     * that is stored in the `LevelObject` itself.
+     */
+    pub fn value(&self) -> u8 {
+        match self {
+            // Y offset 0x0 -> 0xb
+            Self::QuestionBlockPowerup => 0x00,
+            Self::QuestionBlockCoin => 0x01,
+            Self::HiddenBlockCoin => 0x02,
+            Self::HiddenBlockExtraLife => 0x03,
+            Self::BrickPowerup => 0x04,
+            Self::BrickVine => 0x05,
+            Self::BrickStar => 0x06,
+            Self::BrickMultiCoinBlock => 0x07,
+            Self::BrickExtraLife => 0x08,
+            Self::SidewaysPipe => 0x09,
+            Self::UsedBlock => 0x0a,
+            Self::Spring => 0x0b,
+            Self::IslandOrCannon(len) => 0x10 | Self::nibble(*len, 1),
+            Self::HorizontalBrick(len) => 0x20 | Self::nibble(*len, 1),
+            Self::HorizontalBlock(len) => 0x30 | Self::nibble(*len, 1),
+            Self::HorizontalCoin(len) => 0x40 | Self::nibble(*len, 1),
+            Self::VerticalBrick(len) => 0x50 | Self::nibble(*len, 1),
+            Self::VerticalBlock(len) => 0x60 | Self::nibble(*len, 1),
+            Self::PipeNoEntry(len) => 0x70 | Self::nibble(*len, 2),
+            Self::PipeEntry(len) => 0x78 | Self::nibble(*len, 2),
+
+            // Y offset 0xc
+            Self::Hole(len) => Self::nibble(*len, 1),
+            Self::BalanceHorizontalRope(len) => 0x10 | Self::nibble(*len, 1),
+            Self::BridgeY7(len) => 0x20 | Self::nibble(*len, 1),
+            Self::BridgeY8(len) => 0x30 | Self::nibble(*len, 1),
+            Self::BridgeY10(len) => 0x40 | Self::nibble(*len, 1),
+            Self::FilledHole(len) => 0x50 | Self::nibble(*len, 1),
+            Self::HorizontalQuestionBlockY3(len) => {
+                0x60 | Self::nibble(*len, 1)
+            }
+            Self::HorizontalQuestionBlockY7(len) => {
+                0x70 | Self::nibble(*len, 1)
+            }
+
+            // Y offset 0xd
+            Self::PageSkip(byte) => *byte,
+            Self::ReverseLPipe => 0x40,
+            Self::FlagPole => 0x41,
+            Self::CastleAxe => 0x42,
+            Self::AxeRope => 0x43,
+            Self::CastleBridge => 0x44,
+            Self::ScrollStopWarpZone => 0x45,
+            Self::ScrollStop => 0x46,
+            Self::ScrollStopAlternate => 0x47,
+            Self::RedCheepCheep => 0x48,
+            Self::ContinuousBulletBillsOrCheepCheeps => 0x49,
+            Self::StopContinuation => 0x4a,
+            Self::LoopCommand => 0x4b,
+
+            // Y offset 0xe
+            Self::LayoutEmpty(scenery) => scenery.value() << 4,
+            Self::LayoutFloor1Mddle0Ceiling0(scenery) => {
+                scenery.value() << 4 | 0x1
+            }
+            Self::LayoutFloor1Mddle0Ceiling1(scenery) => {
+                scenery.value() << 4 | 0x2
+            }
+            Self::LayoutFloor1Mddle0Ceiling3(scenery) => {
+                scenery.value() << 4 | 0x3
+            }
+            Self::LayoutFloor1Mddle0Ceiling4(scenery) => {
+                scenery.value() << 4 | 0x4
+            }
+            Self::LayoutFloor1Mddle0Ceiling8(scenery) => {
+                scenery.value() << 4 | 0x5
+            }
+            Self::LayoutFloor4Mddle0Ceiling1(scenery) => {
+                scenery.value() << 4 | 0x6
+            }
+            Self::LayoutFloor4Mddle0Ceiling3(scenery) => {
+                scenery.value() << 4 | 0x7
+            }
+            Self::LayoutFloor4Mddle0Ceiling4(scenery) => {
+                scenery.value() << 4 | 0x8
+            }
+            Self::LayoutFloor5Mddle0Ceiling1(scenery) => {
+                scenery.value() << 4 | 0x9
+            }
+            Self::LayoutFloor0Mddle0Ceiling1(scenery) => {
+                scenery.value() << 4 | 0xa
+            }
+            Self::LayoutFloor5Mddle0Ceiling4(scenery) => {
+                scenery.value() << 4 | 0xb
+            }
+            Self::LayoutFloor8Mddle0Ceiling1(scenery) => {
+                scenery.value() << 4 | 0xc
+            }
+            Self::LayoutFloor1Mddle5Ceiling1(scenery) => {
+                scenery.value() << 4 | 0xd
+            }
+            Self::LayoutFloor1Mddle4Ceiling1(scenery) => {
+                scenery.value() << 4 | 0xe
+            }
+            Self::LayoutFull(scenery) => scenery.value() << 4 | 0xf,
+            Self::ChangeBackground(kind) => 0x40 | kind.value(),
+
+            // Y offset 0xf
+            Self::LiftRope => 0x00,
+            Self::BalanceLiftVerticalRope(len) => 0x10 | Self::nibble(*len, 1),
+            Self::BigCastle => 0x20,
+            Self::Castle(row) => 0x20 | row,
+            Self::Staircase(len) => 0x30 | Self::nibble(*len, 1),
+            Self::TallReverseLPipe(y) => 0x40 | y,
+            Self::Nothing => 0x60,
+
+            Self::Invalid(byte) => *byte,
+        }
+    }
+
+    /// Turn a length back into the low nibble it was decoded from.
+    fn nibble(len: u8, offset: u8) -> u8 {
+        len.wrapping_sub(offset) & 0x0f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Level;
+
+    #[test]
+    fn test_positions() {
+        let bytes = [
+            0x07, 0x81, // ? block, new page
+            0x47, 0x24, // brick on the same page
+            0x0d, 0x05, // page skip to page 5
+            0x27, 0x80, // ? block, new page
+            0xfd,
+        ];
+        let object_data = LevelObjectData::from_bytes(&bytes).unwrap();
+
+        let pages: Vec<u8> =
+            object_data.positions().iter().map(|pos| pos.page).collect();
+        assert_eq!(pages, [1, 1, 5, 6]);
+
+        let xs: Vec<u16> =
+            object_data.positions().iter().map(|pos| pos.x()).collect();
+        assert_eq!(xs, [16, 20, 80, 98]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            LevelObjectData::from_bytes(&[0x07, 0x81, 0x47]).unwrap_err(),
+            ParseError::UnexpectedEof { offset: 3 }
+        );
+        assert_eq!(
+            LevelObjectData::from_bytes(&[0x07, 0x81]).unwrap_err(),
+            ParseError::MissingTerminator { terminator: 0xFD, offset: 2 }
+        );
+    }
+
+    #[test]
+    fn test_castle() {
+        let bytes = [
+            0x3f, 0x20, // big castle
+            0x3f, 0x25, // castle from row 5
+            0xfd,
+        ];
+        let object_data = LevelObjectData::from_bytes(&bytes).unwrap();
+
+        let kinds: Vec<&LevelObjectKind> =
+            object_data.objects.iter().map(|object| &object.kind).collect();
+        assert_eq!(
+            kinds,
+            [&LevelObjectKind::BigCastle, &LevelObjectKind::Castle(5)]
+        );
+        assert_eq!(object_data.to_bytes(), bytes);
+
+        // row 0 is the big castle and rows past 15 don't fit in the nibble
+        let area_data = [0x50, 0x21, 0x3f, 0x25, 0xfd];
+        let mut level = Level::from_bytes(&area_data, &[0xff]).unwrap();
+        assert!(level.check_encoding().is_ok());
+        for row in [0, 16] {
+            level.object_data.objects[0].kind = LevelObjectKind::Castle(row);
+            assert!(level.check_encoding().is_err());
+        }
+    }
 }
@@ -0,0 +1,113 @@
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::Level;
+
+/// Version of the level document format, changed whenever the format
+/// changes in a way older readers can't handle.
+pub const LEVEL_FORMAT_VERSION: u32 = 1;
+
+/**
+ * A level as it is exported to JSON or RON.
+ *
+ * The format is documented in docs/level-format.md.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelDocument {
+    pub version: u32,
+    pub level: Level,
+}
+
+impl LevelDocument {
+    pub fn new(level: Level) -> Self {
+        Self { version: LEVEL_FORMAT_VERSION, level }
+    }
+
+    /// Get the level out of a document, checking it can be encoded.
+    pub fn into_level(self) -> Result<Level> {
+        ensure!(
+            self.version == LEVEL_FORMAT_VERSION,
+            "unsupported level format version: {} (expected {})",
+            self.version,
+            LEVEL_FORMAT_VERSION
+        );
+        self.level.check_encoding()?;
+
+        Ok(self.level)
+    }
+}
+
+impl Level {
+    pub fn to_json(&self) -> Result<String> {
+        let document = LevelDocument::new(self.clone());
+        Ok(serde_json::to_string_pretty(&document)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let document: LevelDocument = serde_json::from_str(json)?;
+        document.into_level()
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        let document = LevelDocument::new(self.clone());
+        let config = ron::ser::PrettyConfig::default();
+        Ok(ron::ser::to_string_pretty(&document, config)?)
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self> {
+        let document: LevelDocument = ron::from_str(ron)?;
+        document.into_level()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LevelObjectKind;
+
+    fn test_level() -> Level {
+        let area_data = [0x90, 0x11, 0x07, 0x81, 0x47, 0x24, 0xfd];
+        let enemy_data = [0x1e, 0xc2, 0x00, 0x6b, 0x06, 0xff];
+        Level::from_bytes(&area_data, &enemy_data).unwrap()
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let level = test_level();
+        let json = level.to_json().unwrap();
+        assert_eq!(Level::from_json(&json).unwrap(), level);
+    }
+
+    #[test]
+    fn test_ron_round_trip() {
+        let level = test_level();
+        let ron = level.to_ron().unwrap();
+        assert_eq!(Level::from_ron(&ron).unwrap(), level);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let json = test_level().to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["level"]["level_header"]["time"], "T300");
+        let object = &value["level"]["object_data"]["objects"][1];
+        assert_eq!(object["kind"]["HorizontalBrick"], 5);
+        assert_eq!(object["coordinate"]["column"], 4);
+        let entry = &value["level"]["enemy_data"]["entries"][0];
+        assert_eq!(entry["PipePointer"]["area"]["area_type"], "Underground");
+    }
+
+    #[test]
+    fn test_import_checks_encoding() {
+        let mut level = test_level();
+        level.object_data.objects[1].kind =
+            LevelObjectKind::HorizontalBrick(20);
+        assert!(Level::from_json(&level.to_json().unwrap()).is_err());
+
+        let json = test_level().to_json().unwrap();
+        let json = json.replace("\"version\": 1", "\"version\": 2");
+        assert!(Level::from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{
+    Area, Level, LevelEnemyEntry, LevelEnemyKind, LevelObjectKind, LEVEL_HEIGHT,
+};
+
+/// Objects the game can draw in the same column.
+pub const MAX_OBJECTS_PER_COLUMN: usize = 3;
+
+/// Enemy slots the game has, enemies past these don't spawn until one frees
+/// up.
+pub const MAX_ENEMIES_PER_PAGE: usize = 5;
+
+/// Pages a level can have, page skips and pipe pointers only have 5 bits for
+/// the page.
+pub const MAX_PAGES: usize = 32;
+
+/// The last row block objects can be placed in, rows past it are used for
+/// other objects.
+const MAX_BLOCK_ROW: u8 = 0xb;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// Unusual, but the game handles it.
+    Info,
+    /// The level works, but probably not the way it was meant to.
+    Warning,
+    /// The game can't handle the level.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LevelIssueKind {
+    /// An object the game doesn't have.
+    InvalidObject,
+    /// A block object below the last row blocks can be placed in, it would
+    /// be encoded as a different object.
+    InvalidObjectRow { row: u8 },
+    /// An object with a length of 0, which can't be encoded.
+    ZeroLengthObject,
+    /// An object that runs past the bottom of the screen.
+    ObjectPastBottom { bottom: usize },
+    /// More objects start in a column than the game can draw at once.
+    TooManyObjectsInColumn { count: usize },
+    /// An enemy the game doesn't have.
+    InvalidEnemy,
+    /// A pipe pointer to an area that isn't in the rom.
+    MissingPipeDestination { area: Area },
+    /// More enemies on a page than the game has enemy slots.
+    TooManyEnemiesOnPage { count: usize },
+    /// The level has no flagpole or axe, so it has to be left through a pipe
+    /// or a vine.
+    MissingLevelEnd,
+    /// The level has more pages than the game can address.
+    TooManyPages { pages: usize },
+}
+
+impl fmt::Display for LevelIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidObject => write!(f, "unknown object"),
+            Self::InvalidObjectRow { row } => write!(
+                f,
+                "block object in row {}, blocks can only be placed up to row \
+                 {}",
+                row, MAX_BLOCK_ROW
+            ),
+            Self::ZeroLengthObject => write!(f, "object has a length of 0"),
+            Self::ObjectPastBottom { bottom } => write!(
+                f,
+                "object runs past the bottom of the screen (row {})",
+                bottom
+            ),
+            Self::TooManyObjectsInColumn { count } => write!(
+                f,
+                "{} objects start in this column, only {} are drawn",
+                count, MAX_OBJECTS_PER_COLUMN
+            ),
+            Self::InvalidEnemy => write!(f, "unknown enemy"),
+            Self::MissingPipeDestination { area } => {
+                write!(f, "pipe pointer to {}, which isn't in the rom", area)
+            }
+            Self::TooManyEnemiesOnPage { count } => write!(
+                f,
+                "{} enemies on the page, the game only has {} enemy slots",
+                count, MAX_ENEMIES_PER_PAGE
+            ),
+            Self::MissingLevelEnd => write!(f, "no flagpole or axe"),
+            Self::TooManyPages { pages } => write!(
+                f,
+                "level is {} pages long, the game can only address {}",
+                pages, MAX_PAGES
+            ),
+        }
+    }
+}
+
+/**
+ * A problem found by `Level::validate`.
+ *
+ * `index` is the index of the object or enemy data entry (depending on the
+ * kind) the issue is about, issues about the whole level or a page don't
+ * have one.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LevelIssue {
+    pub severity: Severity,
+    pub kind: LevelIssueKind,
+    pub index: Option<usize>,
+    pub page: Option<u8>,
+}
+
+impl LevelIssue {
+    pub fn new(
+        severity: Severity,
+        kind: LevelIssueKind,
+        index: Option<usize>,
+        page: Option<u8>,
+    ) -> Self {
+        Self { severity, kind, index, page }
+    }
+
+    /// What `index` points at.
+    fn entry_name(&self) -> &'static str {
+        match self.kind {
+            LevelIssueKind::InvalidEnemy
+            | LevelIssueKind::MissingPipeDestination { .. } => {
+                "enemy data entry"
+            }
+            _ => "object",
+        }
+    }
+}
+
+impl fmt::Display for LevelIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        match (self.index, self.page) {
+            (Some(index), Some(page)) => {
+                write!(f, "{} {} (page {}): ", self.entry_name(), index, page)?
+            }
+            (None, Some(page)) => write!(f, "page {}: ", page)?,
+            _ => (),
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Level {
+    /**
+     * Find structural problems the game can't handle, or that are most
+     * likely mistakes, like unknown objects or more enemies than the game
+     * has slots for.
+     *
+     * Pipe pointers are checked against `areas`, the areas in the rom (see
+     * `Rom::areas`).  Levels that pass `validate` can still fail
+     * `check_encoding`.
+     */
+    pub fn validate(&self, areas: &[Area]) -> Vec<LevelIssue> {
+        let mut issues = vec![];
+        self.validate_objects(&mut issues);
+        self.validate_enemies(areas, &mut issues);
+
+        let has_end = self.object_data.objects.iter().any(|object| {
+            matches!(
+                object.kind,
+                LevelObjectKind::FlagPole | LevelObjectKind::CastleAxe
+            )
+        });
+        if !has_end {
+            issues.push(LevelIssue::new(
+                Severity::Info,
+                LevelIssueKind::MissingLevelEnd,
+                None,
+                None,
+            ));
+        }
+
+        let pages = self
+            .object_data
+            .positions()
+            .iter()
+            .chain(self.enemy_data.positions().iter())
+            .map(|position| position.page as usize + 1)
+            .max()
+            .unwrap_or(0);
+        if pages > MAX_PAGES {
+            issues.push(LevelIssue::new(
+                Severity::Error,
+                LevelIssueKind::TooManyPages { pages },
+                None,
+                None,
+            ));
+        }
+
+        issues
+    }
+
+    fn validate_objects(&self, issues: &mut Vec<LevelIssue>) {
+        let positions = self.object_data.positions();
+        let mut columns: HashMap<u16, usize> = HashMap::new();
+        for position in &positions {
+            *columns.entry(position.x()).or_default() += 1;
+        }
+
+        // the objects seen so far in each column
+        let mut seen: HashMap<u16, usize> = HashMap::new();
+        for (idx, (object, position)) in
+            self.object_data.objects.iter().zip(positions).enumerate()
+        {
+            let mut issue = |severity, kind| {
+                issues.push(LevelIssue::new(
+                    severity,
+                    kind,
+                    Some(idx),
+                    Some(position.page),
+                ));
+            };
+
+            let seen = seen.entry(position.x()).or_default();
+            *seen += 1;
+            if *seen == MAX_OBJECTS_PER_COLUMN + 1 {
+                let count = columns[&position.x()];
+                issue(
+                    Severity::Warning,
+                    LevelIssueKind::TooManyObjectsInColumn { count },
+                );
+            }
+
+            let row = object.coordinate.row;
+            if let LevelObjectKind::Invalid(_) = object.kind {
+                issue(Severity::Error, LevelIssueKind::InvalidObject);
+                continue;
+            }
+            if is_block_object(&object.kind) && row > MAX_BLOCK_ROW {
+                issue(
+                    Severity::Error,
+                    LevelIssueKind::InvalidObjectRow { row },
+                );
+                continue;
+            }
+
+            if object_length(&object.kind) == Some(0) {
+                issue(Severity::Error, LevelIssueKind::ZeroLengthObject);
+                continue;
+            }
+
+            // levels that weren't encoded can have any row and height
+            let height = match object.kind {
+                LevelObjectKind::VerticalBrick(len)
+                | LevelObjectKind::VerticalBlock(len)
+                | LevelObjectKind::PipeNoEntry(len)
+                | LevelObjectKind::PipeEntry(len) => len,
+                _ => continue,
+            };
+            let bottom = (row as usize + height as usize).saturating_sub(1);
+            if bottom >= LEVEL_HEIGHT {
+                issue(
+                    Severity::Warning,
+                    LevelIssueKind::ObjectPastBottom { bottom },
+                );
+            }
+        }
+    }
+
+    fn validate_enemies(&self, areas: &[Area], issues: &mut Vec<LevelIssue>) {
+        let mut pages: Vec<(u8, usize)> = vec![];
+
+        let entries = &self.enemy_data.entries;
+        for (idx, (entry, position)) in
+            entries.iter().zip(self.enemy_data.positions()).enumerate()
+        {
+            let page = position.page;
+            match entry {
+                LevelEnemyEntry::Enemy(enemy) => {
+                    if let LevelEnemyKind::Invalid(_) = enemy.kind {
+                        issues.push(LevelIssue::new(
+                            Severity::Error,
+                            LevelIssueKind::InvalidEnemy,
+                            Some(idx),
+                            Some(page),
+                        ));
+                        continue;
+                    }
+
+                    let slots = enemy_slots(&enemy.kind);
+                    match pages.iter_mut().find(|(other, _)| *other == page) {
+                        Some((_, count)) => *count += slots,
+                        None => pages.push((page, slots)),
+                    }
+                }
+                LevelEnemyEntry::PipePointer(pipe_pointer) => {
+                    if !areas.contains(&pipe_pointer.area) {
+                        issues.push(LevelIssue::new(
+                            Severity::Error,
+                            LevelIssueKind::MissingPipeDestination {
+                                area: pipe_pointer.area,
+                            },
+                            Some(idx),
+                            Some(page),
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (page, count) in pages {
+            if count > MAX_ENEMIES_PER_PAGE {
+                issues.push(LevelIssue::new(
+                    Severity::Warning,
+                    LevelIssueKind::TooManyEnemiesOnPage { count },
+                    None,
+                    Some(page),
+                ));
+            }
+        }
+    }
+}
+
+/// Objects that are placed in rows 0 to 11 (the rest of the rows are used
+/// for holes, bridges, commands and layouts).
+fn is_block_object(kind: &LevelObjectKind) -> bool {
+    use LevelObjectKind::*;
+
+    matches!(
+        kind,
+        QuestionBlockPowerup
+            | QuestionBlockCoin
+            | HiddenBlockCoin
+            | HiddenBlockExtraLife
+            | BrickPowerup
+            | BrickVine
+            | BrickStar
+            | BrickMultiCoinBlock
+            | BrickExtraLife
+            | SidewaysPipe
+            | UsedBlock
+            | Spring
+            | IslandOrCannon(_)
+            | HorizontalBrick(_)
+            | HorizontalBlock(_)
+            | HorizontalCoin(_)
+            | VerticalBrick(_)
+            | VerticalBlock(_)
+            | PipeNoEntry(_)
+            | PipeEntry(_)
+    )
+}
+
+/// The length (or height or width) of objects that have one.
+fn object_length(kind: &LevelObjectKind) -> Option<u8> {
+    use LevelObjectKind::*;
+
+    match *kind {
+        IslandOrCannon(len)
+        | HorizontalBrick(len)
+        | HorizontalBlock(len)
+        | HorizontalCoin(len)
+        | VerticalBrick(len)
+        | VerticalBlock(len)
+        | PipeNoEntry(len)
+        | PipeEntry(len)
+        | Hole(len)
+        | BalanceHorizontalRope(len)
+        | BridgeY7(len)
+        | BridgeY8(len)
+        | BridgeY10(len)
+        | FilledHole(len)
+        | HorizontalQuestionBlockY3(len)
+        | HorizontalQuestionBlockY7(len)
+        | BalanceLiftVerticalRope(len)
+        | Staircase(len) => Some(len),
+        _ => None,
+    }
+}
+
+/// Enemy slots an enemy data entry takes, generators and other commands
+/// don't take any.
+fn enemy_slots(kind: &LevelEnemyKind) -> usize {
+    use LevelEnemyKind::*;
+
+    match kind {
+        FlyingCheepCheepGenerator
+        | BowserFireGenerator
+        | FireworksGenerator
+        | BulletBillOrCheepCheepGenerator
+        | StopGenerator
+        | WarpZone
+        | PageSkip(_)
+        | Invalid(_) => 0,
+        TwoGoombasY10
+        | TwoGoombasY6
+        | TwoGreenKoopaTroopasY10
+        | TwoGreenKoopaTroopasY6 => 2,
+        ThreeGoombasY10
+        | ThreeGoombasY6
+        | ThreeGreenKoopaTroopasY10
+        | ThreeGreenKoopaTroopasY6 => 3,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AreaType, LevelObject};
+
+    fn issues(area_data: &[u8], enemy_data: &[u8]) -> Vec<LevelIssue> {
+        let level = Level::from_bytes(area_data, enemy_data).unwrap();
+        level.validate(&[Area::new(AreaType::Ground, 0)])
+    }
+
+    #[test]
+    fn test_valid_level() {
+        let area_data = [
+            0x50, 0x21, // header
+            0x27, 0x01, // ? block (coin)
+            0x79, 0x70, // pipe, 2 high
+            0x8d, 0xc1, // flagpole, new page
+            0xfd,
+        ];
+        let enemy_data = [
+            0xba, 0x06, // goomba
+            0x1e, 0xa0, 0x00, // pipe pointer to ground-0, new page
+            0xff,
+        ];
+
+        assert_eq!(issues(&area_data, &enemy_data), vec![]);
+    }
+
+    #[test]
+    fn test_object_issues() {
+        let area_data = [
+            0x50, 0x21, // header
+            0x27, 0x0c, // invalid object
+            0x2b, 0x56, // vertical bricks, 7 high from row 11
+            0x47, 0x01, // 4 objects in column 4
+            0x43, 0x01, //
+            0x45, 0x01, //
+            0x48, 0x01, //
+            0xfd,
+        ];
+        let issues = issues(&area_data, &[0xff]);
+
+        assert_eq!(
+            issues,
+            vec![
+                LevelIssue::new(
+                    Severity::Error,
+                    LevelIssueKind::InvalidObject,
+                    Some(0),
+                    Some(0)
+                ),
+                LevelIssue::new(
+                    Severity::Warning,
+                    LevelIssueKind::ObjectPastBottom { bottom: 17 },
+                    Some(1),
+                    Some(0)
+                ),
+                LevelIssue::new(
+                    Severity::Warning,
+                    LevelIssueKind::TooManyObjectsInColumn { count: 4 },
+                    Some(5),
+                    Some(0)
+                ),
+                LevelIssue::new(
+                    Severity::Info,
+                    LevelIssueKind::MissingLevelEnd,
+                    None,
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            issues[2].to_string(),
+            "warning: object 5 (page 0): 4 objects start in this column, only \
+             3 are drawn"
+        );
+    }
+
+    #[test]
+    fn test_object_lengths() {
+        // levels built by hand, which can't be encoded
+        let mut level =
+            Level::from_bytes(&[0x50, 0x21, 0x0d, 0x42, 0xfd], &[0xff])
+                .unwrap();
+        let axe = level.object_data.objects[0].clone();
+        let object = |kind, row| {
+            let mut object = LevelObject { kind, ..axe.clone() };
+            object.coordinate.row = row;
+            object
+        };
+        level.object_data.objects.extend([
+            object(LevelObjectKind::VerticalBrick(0), 0),
+            object(LevelObjectKind::PipeEntry(255), 5),
+        ]);
+
+        assert_eq!(
+            level.validate(&[]),
+            vec![
+                LevelIssue::new(
+                    Severity::Error,
+                    LevelIssueKind::ZeroLengthObject,
+                    Some(1),
+                    Some(0)
+                ),
+                LevelIssue::new(
+                    Severity::Warning,
+                    LevelIssueKind::ObjectPastBottom { bottom: 259 },
+                    Some(2),
+                    Some(0)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enemy_issues() {
+        let area_data = [0x50, 0x21, 0x0d, 0x42, 0xfd]; // axe
+        let enemy_data = [
+            0x1b, 0x38, // 3 goombas
+            0x2b, 0x38, // 3 goombas
+            0x3b, 0x13, // invalid enemy
+            0x1e, 0xc2, 0x00, // pipe pointer to underground-2, new page
+            0x0f, 0x25, // page skip to page 37
+            0xff,
+        ];
+        let issues = issues(&area_data, &enemy_data);
+
+        assert_eq!(
+            issues,
+            vec![
+                LevelIssue::new(
+                    Severity::Error,
+                    LevelIssueKind::InvalidEnemy,
+                    Some(2),
+                    Some(0)
+                ),
+                LevelIssue::new(
+                    Severity::Error,
+                    LevelIssueKind::MissingPipeDestination {
+                        area: Area::new(AreaType::Underground, 2)
+                    },
+                    Some(3),
+                    Some(1)
+                ),
+                LevelIssue::new(
+                    Severity::Warning,
+                    LevelIssueKind::TooManyEnemiesOnPage { count: 6 },
+                    None,
+                    Some(0)
+                ),
+                LevelIssue::new(
+                    Severity::Error,
+                    LevelIssueKind::TooManyPages { pages: 38 },
+                    None,
+                    None
+                ),
+            ]
+        );
+    }
+}
@@ -1,6 +1,14 @@
+mod error;
 mod level;
+mod palette;
+mod patch;
+mod render;
 mod rom;
 
 pub mod util;
+pub use error::*;
 pub use level::*;
+pub use palette::*;
+pub use patch::*;
+pub use render::*;
 pub use rom::*;
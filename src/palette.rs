@@ -0,0 +1,338 @@
+use crate::{
+    AreaType, LevelBackground, LevelHeader, LevelPlatform, ParseError,
+    ParseResult,
+};
+
+/// An RGB color.
+pub type Rgb = [u8; 3];
+
+/// 4 NES color indices, color 0 is the shared background color.
+pub type Palette = [u8; 4];
+
+/// Number of colors the NES can show.
+pub const NUM_COLORS: usize = 64;
+
+/// PPU address of the palettes.
+const PALETTE_PPU_ADDRESS: [u8; 2] = [0x3f, 0x00];
+
+/**
+ * BackgroundColors: the background color of each area type, and of each
+ * background color control (`LevelBackground` 4-7: night, snow, night and
+ * snow, black).
+ */
+const AREA_BACKGROUND_COLORS: [u8; 4] = [0x22, 0x22, 0x0f, 0x0f];
+const BACKGROUND_COLORS: [u8; 4] = [0x0f, 0x22, 0x0f, 0x0f];
+
+/**
+ * The 2C02 (NTSC PPU) colors.
+ *
+ * From: https://www.nesdev.org/wiki/PPU_palettes
+ */
+const PALETTE_2C02: [u32; NUM_COLORS] = [
+    0x666666, 0x002a88, 0x1412a7, 0x3b00a4, 0x5c007e, 0x6e0040, 0x6c0600,
+    0x561d00, 0x333500, 0x0b4800, 0x005200, 0x004f08, 0x00404d, 0x000000,
+    0x000000, 0x000000, 0xadadad, 0x155fd9, 0x4240ff, 0x7527fe, 0xa01acc,
+    0xb71e7b, 0xb53120, 0x994e00, 0x6b6d00, 0x388700, 0x0c9300, 0x008f32,
+    0x007c8d, 0x000000, 0x000000, 0x000000, 0xfffeff, 0x64b0ff, 0x9290ff,
+    0xc676ff, 0xf36aff, 0xfe6ecc, 0xfe8170, 0xea9e22, 0xbcbe00, 0x88d800,
+    0x5ce430, 0x45e082, 0x48cdde, 0x4f4f4f, 0x000000, 0x000000, 0xfffeff,
+    0xc0dfff, 0xd3d2ff, 0xe8c8ff, 0xfbc2ff, 0xfec4ea, 0xfeccc5, 0xf7d8a5,
+    0xe4e594, 0xcfef96, 0xbdf4ab, 0xb3f3cc, 0xb5ebf2, 0xb8b8b8, 0x000000,
+    0x000000,
+];
+
+/**
+ * Maps NES color indices (0-63) to RGB.
+ *
+ * The NES doesn't output RGB, so every emulator picks its own colors.  The
+ * default is the 2C02 palette from the nesdev wiki, others can be loaded
+ * from the .pal files emulators use.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MasterPalette {
+    pub colors: [Rgb; NUM_COLORS],
+}
+
+impl Default for MasterPalette {
+    fn default() -> Self {
+        let colors = PALETTE_2C02.map(|rgb| {
+            let [_, r, g, b] = rgb.to_be_bytes();
+            [r, g, b]
+        });
+
+        Self { colors }
+    }
+}
+
+impl MasterPalette {
+    /**
+     * Load a .pal file: 64 RGB colors, 192 bytes.  Files with the colors for
+     * every combination of the emphasis bits (512 colors) are accepted as
+     * well, only the colors without emphasis are used.
+     */
+    pub fn from_pal(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < NUM_COLORS * 3 {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let mut colors = [[0; 3]; NUM_COLORS];
+        for (color, rgb) in colors.iter_mut().zip(bytes.chunks_exact(3)) {
+            color.copy_from_slice(rgb);
+        }
+
+        Ok(Self { colors })
+    }
+
+    /// Get the RGB color of a NES color index, only the low 6 bits are used.
+    pub fn rgb(&self, color: u8) -> Rgb {
+        self.colors[color as usize % NUM_COLORS]
+    }
+}
+
+/// The 4 background and 4 sprite palettes loaded into the PPU.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palettes {
+    pub background: [Palette; 4],
+    pub sprites: [Palette; 4],
+}
+
+impl Palettes {
+    /// The 32 bytes of palette RAM, background palettes first.
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < 32 {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let palette = |idx: usize| -> Palette {
+            [bytes[idx], bytes[idx + 1], bytes[idx + 2], bytes[idx + 3]]
+        };
+
+        Ok(Self {
+            background: [0, 4, 8, 12].map(palette),
+            sprites: [16, 20, 24, 28].map(palette),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, palette) in bytes
+            .chunks_exact_mut(4)
+            .zip(self.background.iter().chain(&self.sprites))
+        {
+            chunk.copy_from_slice(palette);
+        }
+
+        bytes
+    }
+
+    /// The shared background color.
+    pub fn background_color(&self) -> u8 {
+        self.background[0][0]
+    }
+}
+
+/**
+ * The palette data the game loads into the PPU, stored in the same format
+ * as the rest of the game's PPU writes: a big-endian PPU address, a length,
+ * the data and a 0 terminator.
+ *
+ * | data                  | PPU address | length |
+ * |-----------------------|-------------|--------|
+ * | WaterPaletteData      | $3F00       | 32     |
+ * | GroundPaletteData     | $3F00       | 32     |
+ * | UndergroundPaletteData| $3F00       | 32     |
+ * | CastlePaletteData     | $3F00       | 32     |
+ * | DaySnowPaletteData    | $3F00       | 4      |
+ * | NightSnowPaletteData  | $3F00       | 4      |
+ * | MushroomPaletteData   | $3F00       | 4      |
+ * | BowserPaletteData     | $3F14       | 4      |
+ *
+ * The area palettes are picked by area type, the others replace one palette
+ * of them: the snow palettes for snow levels, the mushroom palette for
+ * levels with mushroom ledges and the bowser palette once bowser shows up.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteTables {
+    pub areas: [Palettes; 4],
+    pub day_snow: Palette,
+    pub night_snow: Palette,
+    pub mushroom: Palette,
+    pub bowser: Palette,
+}
+
+impl PaletteTables {
+    /// Any colors are accepted, but the palette data has to be back to back
+    /// with the same PPU addresses and lengths as the game's.
+    pub fn find(prg: &[u8]) -> Option<Self> {
+        (0..prg.len()).find_map(|idx| Self::from_bytes(&prg[idx..]).ok())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        let mut idx = 0;
+        let mut areas = [Palettes::default(); 4];
+        for palettes in &mut areas {
+            let data =
+                Self::ppu_write(bytes, &mut idx, PALETTE_PPU_ADDRESS, 32)?;
+            *palettes = Palettes::from_bytes(data)?;
+        }
+
+        let mut palette = |address: [u8; 2]| -> ParseResult<Palette> {
+            let data = Self::ppu_write(bytes, &mut idx, address, 4)?;
+            Ok([data[0], data[1], data[2], data[3]])
+        };
+
+        Ok(Self {
+            areas,
+            day_snow: palette(PALETTE_PPU_ADDRESS)?,
+            night_snow: palette(PALETTE_PPU_ADDRESS)?,
+            mushroom: palette(PALETTE_PPU_ADDRESS)?,
+            bowser: palette([0x3f, 0x14])?,
+        })
+    }
+
+    /**
+     * Get the palettes the game uses for a level.
+     *
+     * From: https://gist.github.com/1wErt3r/4048722 (GetAreaPalette and
+     * GetBackgroundColor)
+     */
+    pub fn level_palettes(
+        &self,
+        area_type: AreaType,
+        header: &LevelHeader,
+    ) -> Palettes {
+        let mut palettes = self.areas[area_type.value() as usize];
+
+        match header.background {
+            LevelBackground::DayTimeSnow => {
+                palettes.background[0] = self.day_snow;
+            }
+            LevelBackground::NightTimeSnow => {
+                palettes.background[0] = self.night_snow;
+            }
+            // the castle palette replaces the whole area palette
+            LevelBackground::BlackAndWhite => {
+                palettes = self.areas[AreaType::Castle.value() as usize];
+            }
+            _ => (),
+        }
+        if header.platform == LevelPlatform::OrangeAndMushrooms {
+            palettes.background[0] = self.mushroom;
+        }
+
+        // background color control, values below 4 are foreground scenery
+        let background_color = match header.background.value() {
+            value @ 4..=7 => BACKGROUND_COLORS[value as usize - 4],
+            _ => AREA_BACKGROUND_COLORS[area_type.value() as usize],
+        };
+        for palette in
+            palettes.background.iter_mut().chain(&mut palettes.sprites)
+        {
+            palette[0] = background_color;
+        }
+
+        palettes
+    }
+
+    /// Read one PPU write, checking its address, length and terminator.
+    fn ppu_write<'a>(
+        bytes: &'a [u8],
+        idx: &mut usize,
+        address: [u8; 2],
+        len: usize,
+    ) -> ParseResult<&'a [u8]> {
+        let start = *idx;
+        let end = start + 3 + len;
+        let Some(write) = bytes.get(start..=end) else {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        };
+
+        if write[..2] != address || write[2] as usize != len {
+            return Err(ParseError::unknown_value("palette data", &write[..3]));
+        }
+        if write[3 + len] != 0 {
+            return Err(ParseError::MissingTerminator {
+                terminator: 0,
+                offset: end,
+            });
+        }
+
+        *idx = end + 1;
+        Ok(&write[3..3 + len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette_data() -> Vec<u8> {
+        let mut bytes = vec![];
+        for area in 0..4u8 {
+            bytes.extend([0x3f, 0x00, 0x20]);
+            bytes.extend((0..32).map(|n| (area << 4) | (n % 16)));
+            bytes.push(0x00);
+        }
+        bytes.extend([0x3f, 0x00, 0x04, 0x22, 0x30, 0x00, 0x10, 0x00]);
+        bytes.extend([0x3f, 0x00, 0x04, 0x0f, 0x30, 0x00, 0x10, 0x00]);
+        bytes.extend([0x3f, 0x00, 0x04, 0x22, 0x27, 0x16, 0x0f, 0x00]);
+        bytes.extend([0x3f, 0x14, 0x04, 0x0f, 0x1a, 0x30, 0x27, 0x00]);
+        bytes
+    }
+
+    #[test]
+    fn test_find() {
+        let mut prg = vec![0xea; 100];
+        prg.extend(palette_data());
+
+        let tables = PaletteTables::find(&prg).unwrap();
+        assert_eq!(tables.areas[1].background[1], [0x14, 0x15, 0x16, 0x17]);
+        assert_eq!(tables.areas[3].sprites[3], [0x3c, 0x3d, 0x3e, 0x3f]);
+        assert_eq!(tables.mushroom, [0x22, 0x27, 0x16, 0x0f]);
+        assert_eq!(tables.bowser, [0x0f, 0x1a, 0x30, 0x27]);
+
+        // a missing terminator
+        let mut prg = palette_data();
+        prg[35] = 0xff;
+        assert!(PaletteTables::find(&prg).is_none());
+    }
+
+    #[test]
+    fn test_level_palettes() {
+        let tables = PaletteTables::from_bytes(&palette_data()).unwrap();
+        let mut header = LevelHeader::from_bytes(&[0x10, 0x01]).unwrap();
+
+        let palettes = tables.level_palettes(AreaType::Ground, &header);
+        assert_eq!(palettes.background_color(), 0x22);
+        assert_eq!(palettes.background[2], [0x22, 0x19, 0x1a, 0x1b]);
+        assert_eq!(palettes.sprites[0][0], 0x22);
+
+        header.background = LevelBackground::NightTimeSnow;
+        let palettes = tables.level_palettes(AreaType::Ground, &header);
+        assert_eq!(palettes.background[0], [0x0f, 0x30, 0x00, 0x10]);
+
+        header.background = LevelBackground::DayTime;
+        header.platform = LevelPlatform::OrangeAndMushrooms;
+        let palettes = tables.level_palettes(AreaType::Castle, &header);
+        assert_eq!(palettes.background[0], [0x0f, 0x27, 0x16, 0x0f]);
+
+        header.background = LevelBackground::BlackAndWhite;
+        header.platform = LevelPlatform::GreenAndTrees;
+        let palettes = tables.level_palettes(AreaType::Ground, &header);
+        assert_eq!(palettes.background[2], [0x0f, 0x39, 0x3a, 0x3b]);
+        assert_eq!(palettes.sprites[1], [0x0f, 0x35, 0x36, 0x37]);
+    }
+
+    #[test]
+    fn test_master_palette() {
+        let palette = MasterPalette::default();
+        assert_eq!(palette.rgb(0x0f), [0, 0, 0]);
+        assert_eq!(palette.rgb(0x30), [0xff, 0xfe, 0xff]);
+
+        let bytes: Vec<u8> = (0..192).map(|n| n as u8).collect();
+        let palette = MasterPalette::from_pal(&bytes).unwrap();
+        assert_eq!(palette.rgb(1), [3, 4, 5]);
+        assert!(MasterPalette::from_pal(&bytes[1..]).is_err());
+    }
+}
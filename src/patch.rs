@@ -0,0 +1,5 @@
+mod bps;
+mod ips;
+
+pub use bps::*;
+pub use ips::*;
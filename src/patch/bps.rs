@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::mem;
+
+use crate::{Offset, ParseError, ParseResult};
+
+const BPS_MAGIC: &[u8] = b"BPS1";
+/// The source, target and patch checksums at the end of the patch.
+const FOOTER_SIZE: usize = 12;
+
+/// Shortest match worth a copy instead of storing the bytes.
+const MIN_COPY_SIZE: usize = 4;
+/// Earlier places with the same bytes that are tried for a copy.
+const MAX_CANDIDATES: usize = 32;
+/// How much larger than the source a target can be.  The checksums are easy
+/// to forge, so a patch's sizes can't be trusted to be reasonable.
+const MAX_TARGET_GROWTH: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BpsAction {
+    /// Copy bytes from the same offset in the source.
+    SourceRead { len: usize },
+    /// Bytes stored in the patch.
+    TargetRead(Vec<u8>),
+    /// Copy bytes from anywhere in the source.
+    SourceCopy { offset: Offset, len: usize },
+    /// Copy bytes already written to the target, the copy can run into the
+    /// bytes it writes (for runs of the same bytes).
+    TargetCopy { offset: Offset, len: usize },
+}
+
+impl BpsAction {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::SourceRead { len }
+            | Self::SourceCopy { len, .. }
+            | Self::TargetCopy { len, .. } => *len,
+            Self::TargetRead(bytes) => bytes.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/**
+ * A BPS patch: actions that build the target out of the source, with CRC32
+ * checksums of the source and target so a patch is only ever applied to the
+ * file it was made for.
+ *
+ * From: https://github.com/blakesmith/rombp/blob/master/docs/bps_spec.md
+ *
+ * | data                              | size     |
+ * |-----------------------------------|----------|
+ * | "BPS1"                            | 4        |
+ * | source size                       | number   |
+ * | target size                       | number   |
+ * | metadata size                     | number   |
+ * | metadata                          | size     |
+ * | actions:                          |          |
+ * |   (length - 1) << 2 \| command    | number   |
+ * |   bytes (TargetRead)              | length   |
+ * |   relative offset (*Copy)         | number   |
+ * | source checksum                   | 4        |
+ * | target checksum                   | 4        |
+ * | patch checksum                    | 4        |
+ *
+ * Numbers are variable length, 7 bits per byte with the top bit set on the
+ * last byte.  Copy offsets are relative to where the last copy of the same
+ * kind ended, with the sign in the low bit.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BpsPatch {
+    pub source_size: usize,
+    pub target_size: usize,
+    pub metadata: Vec<u8>,
+    pub actions: Vec<BpsAction>,
+    pub source_checksum: u32,
+    pub target_checksum: u32,
+}
+
+impl BpsPatch {
+    /// Parse a patch, the patch's own checksum has to match.
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if !bytes.starts_with(BPS_MAGIC) {
+            return Err(ParseError::unknown_value(
+                "BPS header",
+                bytes.get(..BPS_MAGIC.len()).unwrap_or(bytes),
+            ));
+        }
+        let footer = bytes
+            .len()
+            .checked_sub(FOOTER_SIZE)
+            .filter(|footer| *footer >= BPS_MAGIC.len())
+            .ok_or(ParseError::UnexpectedEof { offset: bytes.len() })?;
+
+        let checksum = |idx: usize| {
+            u32::from_le_bytes([
+                bytes[idx],
+                bytes[idx + 1],
+                bytes[idx + 2],
+                bytes[idx + 3],
+            ])
+        };
+        let expected = checksum(footer + 8);
+        let actual = crc32fast::hash(&bytes[..footer + 8]);
+        if actual != expected {
+            return Err(ParseError::ChecksumMismatch {
+                kind: "patch",
+                expected,
+                actual,
+            });
+        }
+
+        let mut reader =
+            Reader { bytes: &bytes[..footer], idx: BPS_MAGIC.len() };
+        let source_size = reader.number()?;
+        let target_size = reader.number()?;
+        let metadata_size = reader.number()?;
+        let metadata = reader.bytes(metadata_size)?.to_vec();
+
+        let mut actions = vec![];
+        let (mut source_offset, mut target_offset) = (0, 0);
+        while reader.idx < footer {
+            let data = reader.number()?;
+            let len = (data >> 2) + 1;
+            let action = match data & 3 {
+                0 => BpsAction::SourceRead { len },
+                1 => BpsAction::TargetRead(reader.bytes(len)?.to_vec()),
+                2 => {
+                    let offset = reader.offset(source_offset)?;
+                    source_offset = copy_end(offset, len)?;
+                    BpsAction::SourceCopy { offset, len }
+                }
+                _ => {
+                    let offset = reader.offset(target_offset)?;
+                    target_offset = copy_end(offset, len)?;
+                    BpsAction::TargetCopy { offset, len }
+                }
+            };
+            actions.push(action);
+        }
+
+        Ok(Self {
+            source_size,
+            target_size,
+            metadata,
+            actions,
+            source_checksum: checksum(footer),
+            target_checksum: checksum(footer + 4),
+        })
+    }
+
+    /// Encode the patch, empty actions can't be stored and are left out.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = BPS_MAGIC.to_vec();
+        write_number(&mut bytes, self.source_size);
+        write_number(&mut bytes, self.target_size);
+        write_number(&mut bytes, self.metadata.len());
+        bytes.extend(&self.metadata);
+
+        let (mut source_offset, mut target_offset) = (0, 0);
+        for action in self.actions.iter().filter(|action| !action.is_empty()) {
+            let command = match action {
+                BpsAction::SourceRead { .. } => 0,
+                BpsAction::TargetRead(_) => 1,
+                BpsAction::SourceCopy { .. } => 2,
+                BpsAction::TargetCopy { .. } => 3,
+            };
+            write_number(&mut bytes, (action.len() - 1) << 2 | command);
+
+            match action {
+                BpsAction::SourceRead { .. } => {}
+                BpsAction::TargetRead(data) => bytes.extend(data),
+                BpsAction::SourceCopy { offset, len } => {
+                    write_offset(&mut bytes, source_offset, *offset);
+                    source_offset = offset + len;
+                }
+                BpsAction::TargetCopy { offset, len } => {
+                    write_offset(&mut bytes, target_offset, *offset);
+                    target_offset = offset + len;
+                }
+            }
+        }
+
+        bytes.extend(self.source_checksum.to_le_bytes());
+        bytes.extend(self.target_checksum.to_le_bytes());
+        bytes.extend(crc32fast::hash(&bytes).to_le_bytes());
+
+        bytes
+    }
+
+    /**
+     * Create a patch that turns `source` into `target`.
+     *
+     * Bytes are read from the same place in the source where they didn't
+     * change, copied from elsewhere in the source or target when they moved
+     * or repeat, and stored in the patch otherwise.
+     */
+    pub fn create(source: &[u8], target: &[u8]) -> Self {
+        let mut source_index: HashMap<&[u8], Vec<Offset>> = HashMap::new();
+        for (offset, key) in source.windows(MIN_COPY_SIZE).enumerate() {
+            source_index.entry(key).or_default().push(offset);
+        }
+        let mut target_index: HashMap<&[u8], Vec<Offset>> = HashMap::new();
+
+        let mut actions = vec![];
+        let mut literal = vec![];
+        let (mut out, mut indexed) = (0, 0);
+        while out < target.len() {
+            let rest = &target[out..];
+            let mut best = BpsAction::SourceRead {
+                len: common_len(source.get(out..).unwrap_or_default(), rest),
+            };
+
+            if let Some(key) = rest.get(..MIN_COPY_SIZE) {
+                let candidates = |index: &HashMap<&[u8], Vec<Offset>>| {
+                    index
+                        .get(key)
+                        .map(|offsets| {
+                            offsets.iter().rev().take(MAX_CANDIDATES).copied()
+                        })
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>()
+                };
+                for offset in candidates(&source_index) {
+                    let len = common_len(&source[offset..], rest);
+                    if len > best.len() {
+                        best = BpsAction::SourceCopy { offset, len };
+                    }
+                }
+                // target offsets are all before `out`, so this also finds
+                // copies that run into the bytes they write
+                for offset in candidates(&target_index) {
+                    let len = common_len(&target[offset..], rest);
+                    if len > best.len() {
+                        best = BpsAction::TargetCopy { offset, len };
+                    }
+                }
+            }
+
+            if best.len() >= MIN_COPY_SIZE {
+                if !literal.is_empty() {
+                    actions
+                        .push(BpsAction::TargetRead(mem::take(&mut literal)));
+                }
+                out += best.len();
+                actions.push(best);
+            } else {
+                literal.push(target[out]);
+                out += 1;
+            }
+
+            while indexed + MIN_COPY_SIZE <= out {
+                let key = &target[indexed..indexed + MIN_COPY_SIZE];
+                target_index.entry(key).or_default().push(indexed);
+                indexed += 1;
+            }
+        }
+        if !literal.is_empty() {
+            actions.push(BpsAction::TargetRead(literal));
+        }
+
+        Self {
+            source_size: source.len(),
+            target_size: target.len(),
+            metadata: vec![],
+            actions,
+            source_checksum: crc32fast::hash(source),
+            target_checksum: crc32fast::hash(target),
+        }
+    }
+
+    /**
+     * Apply the patch.  The source has to be the file the patch was made
+     * for, and the result has to come out as the file it was made from.
+     */
+    pub fn apply(&self, source: &[u8]) -> ParseResult<Vec<u8>> {
+        let actual = crc32fast::hash(source);
+        if actual != self.source_checksum {
+            return Err(ParseError::ChecksumMismatch {
+                kind: "source",
+                expected: self.source_checksum,
+                actual,
+            });
+        }
+
+        if self.target_size > source.len().saturating_add(MAX_TARGET_GROWTH) {
+            return Err(ParseError::unknown_value(
+                "BPS target size",
+                self.target_size,
+            ));
+        }
+
+        let mut target = Vec::with_capacity(self.target_size);
+        for action in &self.actions {
+            let out = target.len();
+            let end = out.checked_add(action.len());
+            if end.filter(|&end| end <= self.target_size).is_none() {
+                return Err(ParseError::UnexpectedEof {
+                    offset: self.target_size,
+                });
+            }
+
+            match action {
+                BpsAction::SourceRead { len } => {
+                    target.extend(slice(source, out, *len)?);
+                }
+                BpsAction::TargetRead(bytes) => target.extend(bytes),
+                BpsAction::SourceCopy { offset, len } => {
+                    target.extend(slice(source, *offset, *len)?);
+                }
+                BpsAction::TargetCopy { offset, len } => {
+                    if *offset >= out {
+                        return Err(ParseError::UnexpectedEof { offset: out });
+                    }
+                    // byte by byte, the copy can read what it just wrote
+                    for idx in *offset..offset + len {
+                        target.push(target[idx]);
+                    }
+                }
+            }
+        }
+
+        let actual = crc32fast::hash(&target);
+        if actual != self.target_checksum {
+            return Err(ParseError::ChecksumMismatch {
+                kind: "target",
+                expected: self.target_checksum,
+                actual,
+            });
+        }
+
+        Ok(target)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    idx: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> ParseResult<&[u8]> {
+        let bytes = slice(self.bytes, self.idx, len)?;
+        self.idx += len;
+
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> ParseResult<usize> {
+        let start = self.idx;
+        let too_large =
+            || ParseError::unknown_value("BPS number", &self.bytes[start..]);
+
+        let (mut value, mut shift) = (0usize, 1usize);
+        loop {
+            let byte =
+                *self.bytes.get(self.idx).ok_or(ParseError::UnexpectedEof {
+                    offset: self.bytes.len(),
+                })?;
+            self.idx += 1;
+
+            value = (byte as usize & 0x7f)
+                .checked_mul(shift)
+                .and_then(|part| value.checked_add(part))
+                .ok_or_else(too_large)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_mul(0x80).ok_or_else(too_large)?;
+            value = value.checked_add(shift).ok_or_else(too_large)?;
+        }
+    }
+
+    /// Read a copy offset relative to `base`.
+    fn offset(&mut self, base: Offset) -> ParseResult<Offset> {
+        let data = self.number()?;
+        let delta = isize::try_from(data >> 1)
+            .map_err(|_| ParseError::unknown_value("BPS copy offset", data))?;
+        let delta = if data & 1 == 1 { -delta } else { delta };
+
+        base.checked_add_signed(delta)
+            .ok_or(ParseError::unknown_value("BPS copy offset", delta))
+    }
+}
+
+/// Where a copy ends, the next copy's offset is relative to it.
+fn copy_end(offset: Offset, len: usize) -> ParseResult<Offset> {
+    offset
+        .checked_add(len)
+        .ok_or(ParseError::unknown_value("BPS copy offset", offset))
+}
+
+fn slice(data: &[u8], offset: Offset, len: usize) -> ParseResult<&[u8]> {
+    data.get(offset..)
+        .and_then(|data| data.get(..len))
+        .ok_or(ParseError::UnexpectedEof { offset: data.len() })
+}
+
+fn write_number(bytes: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(0x80 | byte);
+            return;
+        }
+        bytes.push(byte);
+        value -= 1;
+    }
+}
+
+fn write_offset(bytes: &mut Vec<u8>, base: Offset, offset: Offset) {
+    let value = if offset < base {
+        (base - offset) << 1 | 1
+    } else {
+        (offset - base) << 1
+    };
+    write_number(bytes, value);
+}
+
+/// Number of bytes at the start of `a` and `b` that are the same.
+fn common_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers() {
+        for value in [0, 1, 0x7f, 0x80, 0x407f, 0x4080, 0x12345678] {
+            let mut bytes = vec![];
+            write_number(&mut bytes, value);
+            let mut reader = Reader { bytes: &bytes, idx: 0 };
+            assert_eq!(reader.number(), Ok(value));
+            assert_eq!(reader.idx, bytes.len());
+        }
+
+        let mut bytes = vec![];
+        write_number(&mut bytes, 0x80);
+        assert_eq!(bytes, [0x00, 0x80]);
+
+        let mut reader = Reader { bytes: &[0x00; 16], idx: 0 };
+        assert!(reader.number().is_err());
+    }
+
+    #[test]
+    fn test_bps_patch() {
+        let source = b"hello world".to_vec();
+        let target = b"hello, world world!!!!!!!!".to_vec();
+        let patch = BpsPatch {
+            source_size: source.len(),
+            target_size: target.len(),
+            metadata: b"<test/>".to_vec(),
+            actions: vec![
+                BpsAction::SourceRead { len: 5 },
+                BpsAction::TargetRead(b",".to_vec()),
+                BpsAction::SourceCopy { offset: 5, len: 6 },
+                BpsAction::SourceCopy { offset: 5, len: 6 },
+                BpsAction::TargetRead(b"!".to_vec()),
+                BpsAction::TargetCopy { offset: 18, len: 7 },
+            ],
+            source_checksum: crc32fast::hash(&source),
+            target_checksum: crc32fast::hash(&target),
+        };
+
+        assert_eq!(patch.apply(&source), Ok(target.clone()));
+        let bytes = patch.to_bytes();
+        assert_eq!(BpsPatch::from_bytes(&bytes), Ok(patch.clone()));
+
+        // the wrong source
+        assert!(matches!(
+            patch.apply(b"hello there"),
+            Err(ParseError::ChecksumMismatch { kind: "source", .. })
+        ));
+
+        // a damaged patch
+        let mut damaged = bytes.clone();
+        damaged[8] ^= 0xff;
+        assert!(matches!(
+            BpsPatch::from_bytes(&damaged),
+            Err(ParseError::ChecksumMismatch { kind: "patch", .. })
+        ));
+        assert!(BpsPatch::from_bytes(b"BPS1").is_err());
+        assert!(BpsPatch::from_bytes(b"PATCHEOF").is_err());
+
+        // sizes far past the source, which valid checksums don't prevent
+        let huge = BpsPatch { target_size: usize::MAX, ..patch.clone() };
+        assert!(matches!(
+            huge.apply(&source),
+            Err(ParseError::UnknownValue { kind: "BPS target size", .. })
+        ));
+        let huge = BpsPatch {
+            actions: vec![BpsAction::TargetCopy { offset: 0, len: usize::MAX }],
+            ..patch
+        };
+        assert!(huge.apply(&source).is_err());
+
+        // copies that move the offset past the end of memory, with a valid
+        // patch checksum
+        let mut forged = BPS_MAGIC.to_vec();
+        for number in [11, 11, 0] {
+            write_number(&mut forged, number);
+        }
+        for delta in [usize::MAX - 1, 1 << 63] {
+            write_number(&mut forged, ((1 << 62) - 1) << 2 | 2);
+            write_number(&mut forged, delta);
+        }
+        forged.extend([0; 8]);
+        forged.extend(crc32fast::hash(&forged).to_le_bytes());
+        assert!(matches!(
+            BpsPatch::from_bytes(&forged),
+            Err(ParseError::UnknownValue { kind: "BPS copy offset", .. })
+        ));
+    }
+
+    #[test]
+    fn test_create() {
+        let source: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut target = source.clone();
+        target[10] = 0xff; // changed
+        target.copy_within(600..700, 300); // moved
+        target[800..900].fill(0xee); // a run
+        target.truncate(950);
+
+        let patch = BpsPatch::create(&source, &target);
+        assert_eq!(patch.apply(&source), Ok(target.clone()));
+        assert_eq!(BpsPatch::from_bytes(&patch.to_bytes()), Ok(patch.clone()));
+        assert!(patch.to_bytes().len() < 64);
+        assert!(patch
+            .actions
+            .iter()
+            .any(|action| matches!(action, BpsAction::TargetCopy { .. })));
+
+        let patch = BpsPatch::create(&source, &source);
+        assert_eq!(patch.actions, vec![BpsAction::SourceRead { len: 1000 }]);
+        assert_eq!(patch.apply(&source), Ok(source.clone()));
+
+        let patch = BpsPatch::create(&[], &target);
+        assert_eq!(patch.apply(&[]), Ok(target));
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{ensure, Result};
+
+use crate::{Offset, ParseError, ParseResult};
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+
+/// A record can't start at this offset, it reads as the end of the patch.
+const EOF_OFFSET: Offset = 0x454f46;
+/// Offsets are 3 bytes.
+const MAX_OFFSET: Offset = 0xffffff;
+/// Record lengths are 2 bytes.
+const MAX_RECORD_SIZE: usize = 0xffff;
+
+/// Unchanged bytes between changes that are cheaper to copy than to start a
+/// new record for (a record header is 5 bytes).
+const MAX_GAP: usize = 5;
+/// Runs of the same byte at least this long are stored as RLE records.
+const MIN_RLE_SIZE: usize = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpsData {
+    Bytes(Vec<u8>),
+    /// The same byte repeated.
+    Rle {
+        len: u16,
+        byte: u8,
+    },
+}
+
+impl IpsData {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Bytes(bytes) => bytes.len(),
+            Self::Rle { len, .. } => *len as usize,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpsRecord {
+    pub offset: Offset,
+    pub data: IpsData,
+}
+
+/**
+ * An IPS patch: records of bytes to write at an offset, with an optional size
+ * to cut the patched file down to at the end.
+ *
+ * From: https://zerosoft.zophar.net/ips.php
+ *
+ * | data                           | size |
+ * |--------------------------------|------|
+ * | "PATCH"                        | 5    |
+ * | records:                       |      |
+ * |   offset (big-endian)          | 3    |
+ * |   size (big-endian)            | 2    |
+ * |   data (if size isn't 0)       | size |
+ * |   RLE size, byte (if size is 0)| 3    |
+ * | "EOF"                          | 3    |
+ * | truncated size (optional)      | 3    |
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IpsPatch {
+    pub records: Vec<IpsRecord>,
+    pub truncate: Option<usize>,
+}
+
+impl IpsPatch {
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if !bytes.starts_with(IPS_MAGIC) {
+            return Err(ParseError::unknown_value(
+                "IPS header",
+                bytes.get(..IPS_MAGIC.len()).unwrap_or(bytes),
+            ));
+        }
+
+        let read = |idx: usize, len: usize| -> ParseResult<usize> {
+            let data = bytes
+                .get(idx..idx + len)
+                .ok_or(ParseError::UnexpectedEof { offset: bytes.len() })?;
+            Ok(data.iter().fold(0, |value, &byte| value << 8 | byte as usize))
+        };
+
+        let mut records = vec![];
+        let mut idx = IPS_MAGIC.len();
+        loop {
+            if bytes.get(idx..idx + IPS_EOF.len()) == Some(IPS_EOF) {
+                idx += IPS_EOF.len();
+                break;
+            }
+
+            let offset = read(idx, 3)?;
+            let size = read(idx + 3, 2)?;
+            idx += 5;
+            let data = if size == 0 {
+                let len = read(idx, 2)? as u16;
+                let byte = read(idx + 2, 1)? as u8;
+                idx += 3;
+                IpsData::Rle { len, byte }
+            } else {
+                let data = bytes
+                    .get(idx..idx + size)
+                    .ok_or(ParseError::UnexpectedEof { offset: bytes.len() })?;
+                idx += size;
+                IpsData::Bytes(data.to_vec())
+            };
+
+            records.push(IpsRecord { offset, data });
+        }
+
+        let truncate = match bytes.len() - idx {
+            0 => None,
+            _ => Some(read(idx, 3)?),
+        };
+
+        Ok(Self { records, truncate })
+    }
+
+    /// Encode the patch, offsets and sizes have to fit in the format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = IPS_MAGIC.to_vec();
+        for record in &self.records {
+            ensure!(
+                record.offset <= MAX_OFFSET && record.offset != EOF_OFFSET,
+                "IPS records can't start at offset {:#x}",
+                record.offset
+            );
+            bytes.extend(u24_bytes(record.offset));
+
+            match &record.data {
+                IpsData::Bytes(data) => {
+                    ensure!(
+                        !data.is_empty() && data.len() <= MAX_RECORD_SIZE,
+                        "IPS record at {:#x} is {} bytes",
+                        record.offset,
+                        data.len()
+                    );
+                    bytes.extend((data.len() as u16).to_be_bytes());
+                    bytes.extend(data);
+                }
+                IpsData::Rle { len, byte } => {
+                    bytes.extend([0, 0]);
+                    bytes.extend(len.to_be_bytes());
+                    bytes.push(*byte);
+                }
+            }
+        }
+        bytes.extend(IPS_EOF);
+
+        if let Some(truncate) = self.truncate {
+            ensure!(
+                truncate <= MAX_OFFSET,
+                "IPS patches can't truncate to {:#x} bytes",
+                truncate
+            );
+            bytes.extend(u24_bytes(truncate));
+        }
+
+        Ok(bytes)
+    }
+
+    /**
+     * Create a patch that turns `source` into `target`.
+     *
+     * Changes close together are stored in one record, and long runs of the
+     * same byte as RLE records.
+     */
+    pub fn create(source: &[u8], target: &[u8]) -> Result<Self> {
+        ensure!(
+            target.len() <= MAX_OFFSET,
+            "IPS patches only reach {:#x} bytes, target is {:#x} bytes",
+            MAX_OFFSET,
+            target.len()
+        );
+
+        let changed = |idx: usize| source.get(idx) != target.get(idx);
+
+        let mut records = vec![];
+        let mut idx = 0;
+        while idx < target.len() {
+            if !changed(idx) {
+                idx += 1;
+                continue;
+            }
+
+            // a record can't start at "EOF", start it a byte early instead
+            let start = if idx == EOF_OFFSET { idx - 1 } else { idx };
+
+            // extend the record until the next gap of unchanged bytes
+            let mut end = idx + 1;
+            while end < target.len() && end - start < MAX_RECORD_SIZE {
+                let gap = (end..target.len())
+                    .take(MAX_GAP + 1)
+                    .take_while(|&idx| !changed(idx))
+                    .count();
+                if gap > MAX_GAP || end + gap >= target.len() {
+                    break;
+                }
+                end = (end + gap + 1).min(start + MAX_RECORD_SIZE);
+            }
+
+            records.push(record(start, &target[start..end]));
+            idx = end;
+        }
+
+        let truncate = (target.len() < source.len()).then_some(target.len());
+
+        Ok(Self { records, truncate })
+    }
+
+    /// Apply the patch, writing past the end of the data grows it.
+    pub fn apply(&self, data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+
+        for record in &self.records {
+            let end = record.offset + record.data.len();
+            if data.len() < end {
+                data.resize(end, 0);
+            }
+
+            let target = &mut data[record.offset..end];
+            match &record.data {
+                IpsData::Bytes(bytes) => target.copy_from_slice(bytes),
+                IpsData::Rle { byte, .. } => target.fill(*byte),
+            }
+        }
+
+        if let Some(truncate) = self.truncate {
+            data.truncate(truncate);
+        }
+
+        data
+    }
+}
+
+fn u24_bytes(value: usize) -> [u8; 3] {
+    [(value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+/// Store a run of the same byte as RLE when it's long enough to be smaller.
+fn record(offset: Offset, data: &[u8]) -> IpsRecord {
+    let data = if data.len() >= MIN_RLE_SIZE
+        && data.iter().all(|&byte| byte == data[0])
+    {
+        IpsData::Rle { len: data.len() as u16, byte: data[0] }
+    } else {
+        IpsData::Bytes(data.to_vec())
+    };
+
+    IpsRecord { offset, data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ips_patch() {
+        let bytes = [
+            b'P', b'A', b'T', b'C', b'H', //
+            0x00, 0x00, 0x02, 0x00, 0x02, 0xaa, 0xbb, // 2 bytes at 2
+            0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x04, 0xcc, // 4 0xcc at 8
+            b'E', b'O', b'F', //
+            0x00, 0x00, 0x0a, // truncate to 10 bytes
+        ];
+        let patch = IpsPatch::from_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            patch.records,
+            vec![
+                IpsRecord { offset: 2, data: IpsData::Bytes(vec![0xaa, 0xbb]) },
+                IpsRecord {
+                    offset: 8,
+                    data: IpsData::Rle { len: 4, byte: 0xcc }
+                },
+            ]
+        );
+        assert_eq!(patch.truncate, Some(10));
+        assert_eq!(patch.to_bytes().unwrap(), bytes);
+
+        assert_eq!(
+            patch.apply(&[0; 4]),
+            [0, 0, 0xaa, 0xbb, 0, 0, 0, 0, 0xcc, 0xcc]
+        );
+
+        assert!(IpsPatch::from_bytes(b"PATCH\x00\x00").is_err());
+        assert!(IpsPatch::from_bytes(b"PATCHEOF\x00").is_err());
+        assert!(IpsPatch::from_bytes(b"NOPE").is_err());
+    }
+
+    #[test]
+    fn test_create() {
+        let source: Vec<u8> = (0..100).collect();
+        let mut target = source.clone();
+        target[3] = 0xff;
+        target[6] = 0xff; // close enough to share a record
+        target[20..40].fill(0xee); // RLE
+        target.extend([1, 2, 3]);
+
+        let patch = IpsPatch::create(&source, &target).unwrap();
+        assert_eq!(
+            patch.records,
+            vec![
+                IpsRecord {
+                    offset: 3,
+                    data: IpsData::Bytes(vec![0xff, 4, 5, 0xff]),
+                },
+                IpsRecord {
+                    offset: 20,
+                    data: IpsData::Rle { len: 20, byte: 0xee },
+                },
+                IpsRecord { offset: 100, data: IpsData::Bytes(vec![1, 2, 3]) },
+            ]
+        );
+        assert_eq!(patch.apply(&source), target);
+
+        let patch = IpsPatch::create(&target, &source).unwrap();
+        assert_eq!(patch.truncate, Some(100));
+        assert_eq!(patch.apply(&target), source);
+
+        let patch = IpsPatch::create(&source, &source).unwrap();
+        assert_eq!(patch, IpsPatch::default());
+    }
+}
@@ -0,0 +1,11 @@
+mod ascii;
+mod image;
+mod level_renderer;
+mod metatile;
+mod metatile_kind;
+
+pub use ascii::*;
+pub use image::*;
+pub use level_renderer::*;
+pub use metatile::*;
+pub use metatile_kind::*;
@@ -0,0 +1,225 @@
+use std::fmt::Write;
+
+use crate::{
+    AreaType, Level, LevelBackground, LevelEnemyEntry, LevelEnemyKind,
+    LevelRenderer, MetatileKind,
+};
+
+const RESET: &str = "\x1b[0m";
+
+/**
+ * Draws a level as text, one character per metatile:
+ *
+ * | char | metatile                          |
+ * |------|-----------------------------------|
+ * | `#`  | ground and solid blocks           |
+ * | `B`  | bricks (with or without an item)  |
+ * | `?`  | question blocks                   |
+ * | `H`  | hidden blocks                     |
+ * | `u`  | used blocks                       |
+ * | `o`  | coins                             |
+ * | `\|` | pipes                             |
+ * | `=`  | bridges, ledges and lifts         |
+ * | `:`  | tree trunks and mushroom stumps   |
+ * | `C`  | bullet bill cannons               |
+ * | `%`  | castles                           |
+ * | `~`  | water                             |
+ * | `E`  | enemies                           |
+ *
+ * Background scenery is left out.  With `color` the characters are colored
+ * with ANSI escape codes on the background color of the level.
+ */
+pub struct AsciiRenderer<'a> {
+    level: &'a Level,
+    area_type: AreaType,
+    color: bool,
+}
+
+impl<'a> AsciiRenderer<'a> {
+    pub fn new(level: &'a Level, area_type: AreaType, color: bool) -> Self {
+        Self { level, area_type, color }
+    }
+
+    pub fn render(&self) -> String {
+        let grid = LevelRenderer::new(self.level, self.area_type).render();
+
+        let mut rows: Vec<Vec<(char, &str)>> = grid
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|metatile| {
+                        (metatile_char(*metatile), color(*metatile))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let enemy_data = &self.level.enemy_data;
+        for (entry, position) in
+            enemy_data.entries.iter().zip(enemy_data.positions())
+        {
+            let LevelEnemyEntry::Enemy(enemy) = entry else {
+                continue;
+            };
+            let Some(c) = enemy_char(&enemy.kind) else {
+                continue;
+            };
+
+            let row = position.coordinate.row as usize;
+            if let Some(cell) = rows
+                .get_mut(row)
+                .and_then(|row| row.get_mut(position.x() as usize))
+            {
+                *cell = (c, "91");
+            }
+        }
+
+        let mut output = String::new();
+        for row in rows {
+            let mut current = "";
+            if self.color {
+                output.push_str(self.background_color());
+            }
+            for (c, color) in row {
+                if self.color && color != current {
+                    let _ = write!(output, "\x1b[{}m", color);
+                    current = color;
+                }
+                output.push(c);
+            }
+            if self.color {
+                output.push_str(RESET);
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Underground, castle and night levels are black, everything else has
+    /// the blue sky (or water).
+    fn background_color(&self) -> &'static str {
+        let night = matches!(
+            self.level.level_header.background,
+            LevelBackground::NightTime
+                | LevelBackground::NightTimeSnow
+                | LevelBackground::BlackAndWhite
+        );
+
+        match self.area_type {
+            AreaType::Underground | AreaType::Castle => "\x1b[40m",
+            _ if night => "\x1b[40m",
+            _ => "\x1b[48;5;111m",
+        }
+    }
+}
+
+fn metatile_char(metatile: MetatileKind) -> char {
+    use MetatileKind::*;
+
+    match metatile {
+        Ground | CloudGround | WaterBlock | CastleBlock | SolidBlock
+        | HalfBrick => '#',
+        BrickLine | Brick | BrickUnused | BrickLinePowerup | BrickLineVine
+        | BrickLineStar | BrickLineMultiCoin | BrickLineExtraLife
+        | BrickPowerup | BrickVine | BrickStar | BrickMultiCoin
+        | BrickExtraLife | Seaplant => 'B',
+        QuestionBlockCoin | QuestionBlockPowerup => '?',
+        HiddenBlockCoin | HiddenBlockExtraLife => 'H',
+        UsedBlock => 'u',
+        Coin | WaterCoin => 'o',
+        WarpPipeEndLeft
+        | WarpPipeEndRight
+        | PipeEndLeft
+        | PipeEndRight
+        | PipeShaftLeft
+        | PipeShaftRight
+        | SidewaysPipeEndTop
+        | SidewaysPipeShaftTop
+        | SidewaysPipeJointTop
+        | SidewaysPipeEndBottom
+        | SidewaysPipeShaftBottom
+        | SidewaysPipeJointBottom
+        | WaterPipeTop
+        | WaterPipeBottom => '|',
+        Bridge | BowserBridge | TreeLedgeLeft | TreeLedgeMiddle
+        | TreeLedgeRight | MushroomLeft | MushroomMiddle | MushroomRight => '=',
+        TreeTrunk | LedgeStump | MushroomStumpTop | MushroomStumpBottom => ':',
+        CannonBarrel | CannonTop | CannonBottom => 'C',
+        CastleTop | CastleWindowLeft | CastleBrickWall | CastleWindowRight
+        | CastleTopBrick | CastleEntranceTop | CastleEntranceBottom => '%',
+        WaterTop | Water => '~',
+        BridgeGuardrail | HorizontalRope => '-',
+        VerticalRope | FlagpoleShaft => '!',
+        PulleyLeft | PulleyRight => '+',
+        FlagpoleBall | FlagBall => '*',
+        SpringBlank | SpringHalfBrick => 'J',
+        Chain => 's',
+        Axe => 'A',
+        _ => ' ',
+    }
+}
+
+/// ANSI foreground color of a metatile.
+fn color(metatile: MetatileKind) -> &'static str {
+    match metatile_char(metatile) {
+        '#' | 'B' | 'u' | ':' => "33",
+        '?' | 'o' | 'A' => "93",
+        '|' | '=' | '*' | '!' => "92",
+        '~' => "96",
+        _ => "97",
+    }
+}
+
+/// Enemies are drawn as `E` and lifts as `=`, generators and other entries
+/// that don't show up where they are placed aren't drawn.
+fn enemy_char(kind: &LevelEnemyKind) -> Option<char> {
+    use LevelEnemyKind::*;
+
+    match kind {
+        FlyingCheepCheepGenerator
+        | BowserFireGenerator
+        | FireworksGenerator
+        | BulletBillOrCheepCheepGenerator
+        | StopGenerator
+        | WarpZone
+        | ToadOrPrincess
+        | PageSkip(_)
+        | Invalid(_) => None,
+        BalanceLift | LiftUpAndDown | LiftUp | LiftDown | LiftLeftAndRight
+        | LiftFalling | LiftRight | ShortLiftUp | ShortLiftDown => Some('='),
+        _ => Some('E'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LEVEL_HEIGHT;
+
+    #[test]
+    fn test_render() {
+        let area_data = [
+            0x10, 0x01, // no scenery, basic floor
+            0x27, 0x01, // ? block (coin)
+            0x47, 0x21, // 2 bricks
+            0x79, 0x70, // pipe, 2 high
+            0xfd,
+        ];
+        let enemy_data = [0xba, 0x06, 0xff]; // goomba
+        let level = Level::from_bytes(&area_data, &enemy_data).unwrap();
+
+        let text = AsciiRenderer::new(&level, AreaType::Ground, false).render();
+        let rows: Vec<&str> = text.lines().collect();
+
+        assert_eq!(rows.len(), LEVEL_HEIGHT);
+        assert_eq!(rows[7], "  ? BB          ");
+        assert_eq!(rows[9], "       ||       ");
+        assert_eq!(rows[10], "       ||  E    ");
+        assert_eq!(rows[11], "################");
+
+        let text = AsciiRenderer::new(&level, AreaType::Ground, true).render();
+        assert!(text.lines().all(|row| row.ends_with(RESET)));
+        assert!(text.contains("\x1b[93m?"));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    AreaType, Level, LevelEnemyEntry, LevelEnemyKind, LevelRenderer,
+    MasterPalette, MetatileTables, Palettes, PatternTable, Rgb, Tile,
+    LEVEL_HEIGHT,
+};
+
+/// Pixels in a metatile (2x2 tiles of 8x8 pixels).
+pub const METATILE_SIZE: usize = 16;
+
+/// Everything from the rom needed to draw a level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelGraphics {
+    pub background: PatternTable,
+    pub sprites: PatternTable,
+    pub palettes: Palettes,
+    pub metatiles: MetatileTables,
+}
+
+/// An RGB image, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, color: Rgb) -> Self {
+        Self { width, height, pixels: vec![color; width * height] }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Set a pixel, anything outside of the image is dropped.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    /// Encode the image as a PNG file.
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(
+            &mut bytes,
+            self.width as u32,
+            self.height as u32,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let data: Vec<u8> = self.pixels.iter().flatten().copied().collect();
+        encoder.write_header()?.write_image_data(&data)?;
+
+        Ok(bytes)
+    }
+}
+
+/**
+ * Draws a level as a picture, like the classic level maps: the metatiles from
+ * `LevelRenderer` drawn with the game's metatiles, tiles and palettes, and
+ * the enemies on top as their sprites.
+ *
+ * Enemies are drawn with their first animation frame, facing left.  Lifts,
+ * fire bars, Bowser, bullet bills and generators don't have a sprite here
+ * and are left out.
+ */
+pub struct ImageRenderer<'a> {
+    level: &'a Level,
+    area_type: AreaType,
+    graphics: &'a LevelGraphics,
+    master_palette: &'a MasterPalette,
+}
+
+impl<'a> ImageRenderer<'a> {
+    pub fn new(
+        level: &'a Level,
+        area_type: AreaType,
+        graphics: &'a LevelGraphics,
+        master_palette: &'a MasterPalette,
+    ) -> Self {
+        Self { level, area_type, graphics, master_palette }
+    }
+
+    pub fn render(&self) -> Image {
+        let grid = LevelRenderer::new(self.level, self.area_type).render();
+        let palettes = &self.graphics.palettes;
+
+        let mut image = Image::new(
+            grid.width() * METATILE_SIZE,
+            LEVEL_HEIGHT * METATILE_SIZE,
+            self.master_palette.rgb(palettes.background_color()),
+        );
+
+        for (x, column) in grid.columns.iter().enumerate() {
+            for (row, metatile) in column.iter().enumerate() {
+                let Some(metatile) =
+                    self.graphics.metatiles.get(metatile.value())
+                else {
+                    continue;
+                };
+                let palette = palettes.background[metatile.palette as usize];
+                for (idx, tile) in metatile.tiles.iter().enumerate() {
+                    // the tiles go top left, bottom left, top right, bottom
+                    // right
+                    self.draw_tile(
+                        &mut image,
+                        self.graphics.background.tile(*tile),
+                        x * METATILE_SIZE + idx / 2 * 8,
+                        row * METATILE_SIZE + idx % 2 * 8,
+                        palette,
+                        false,
+                    );
+                }
+            }
+        }
+
+        let enemy_data = &self.level.enemy_data;
+        for (entry, position) in
+            enemy_data.entries.iter().zip(enemy_data.positions())
+        {
+            let LevelEnemyEntry::Enemy(enemy) = entry else {
+                continue;
+            };
+            let Some((tiles, palette)) = enemy_sprite(&enemy.kind) else {
+                continue;
+            };
+
+            // the sprite is 2x3 tiles, with the bottom 2 rows in the row the
+            // enemy is placed in
+            let left = position.x() as usize * METATILE_SIZE;
+            let top = position.coordinate.row as usize * METATILE_SIZE;
+            let palette = palettes.sprites[palette as usize];
+            for (idx, tile) in tiles.iter().enumerate() {
+                let Some(y) = (top + idx / 2 * 8).checked_sub(8) else {
+                    continue;
+                };
+                self.draw_tile(
+                    &mut image,
+                    self.graphics.sprites.tile(*tile),
+                    left + idx % 2 * 8,
+                    y,
+                    palette,
+                    true,
+                );
+            }
+        }
+
+        image
+    }
+
+    /// Draw a tile, sprites leave color 0 transparent.
+    fn draw_tile(
+        &self,
+        image: &mut Image,
+        tile: &Tile,
+        left: usize,
+        top: usize,
+        palette: [u8; 4],
+        sprite: bool,
+    ) {
+        for (y, row) in tile.pixels.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                if sprite && *pixel == 0 {
+                    continue;
+                }
+                let color = self.master_palette.rgb(palette[*pixel as usize]);
+                image.set_pixel(left + x, top + y, color);
+            }
+        }
+    }
+}
+
+/**
+ * The sprite tiles (2x3, row by row) and sprite palette of an enemy's first
+ * animation frame.
+ *
+ * From: https://gist.github.com/1wErt3r/4048722 (EnemyGraphicsTable and
+ * EnemyAttributeData)
+ */
+fn enemy_sprite(kind: &LevelEnemyKind) -> Option<([u8; 6], u8)> {
+    use LevelEnemyKind::*;
+
+    let sprite = match kind {
+        GreenKoopaTroopa | GreenKoopaTroopaStationary => {
+            ([0xfc, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9], 1)
+        }
+        RedKoopaTroopa | RedKoopaTroopaWalksOffFloors => {
+            ([0xfc, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9], 2)
+        }
+        GreenKoopaParatroopaLeaping
+        | GreenKoopaParatroopaHorizontal
+        | YellowKoopaParatroopa => ([0x69, 0xa5, 0x6a, 0xa7, 0xa8, 0xa9], 1),
+        RedKoopaParatroopaVertical => ([0x69, 0xa5, 0x6a, 0xa7, 0xa8, 0xa9], 2),
+        BuzzyBeetle => ([0xfc, 0xfc, 0xaa, 0xab, 0xac, 0xad], 3),
+        Goomba | TwoGoombasY10 | ThreeGoombasY10 | TwoGoombasY6
+        | ThreeGoombasY6 => ([0xfc, 0xfc, 0x70, 0x71, 0x72, 0x73], 3),
+        TwoGreenKoopaTroopasY10
+        | ThreeGreenKoopaTroopasY10
+        | TwoGreenKoopaTroopasY6
+        | ThreeGreenKoopaTroopasY6 => ([0xfc, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9], 1),
+        HammerBrother => ([0x7d, 0x7c, 0xd1, 0x8c, 0xd3, 0xd2], 1),
+        Blooper => ([0xfc, 0xfc, 0xdc, 0xdc, 0xdf, 0xdf], 3),
+        GreenCheepCheep => ([0xfc, 0xfc, 0xb2, 0xb3, 0xb4, 0xb5], 1),
+        RedCheepCheep => ([0xfc, 0xfc, 0xb2, 0xb3, 0xb4, 0xb5], 2),
+        Podoboo => ([0xfc, 0xfc, 0xd0, 0xd0, 0xd7, 0xd7], 2),
+        PiranhaPlant => ([0xe5, 0xe5, 0xe6, 0xe6, 0xeb, 0xeb], 1),
+        Lakitu => ([0xb9, 0xb8, 0xbb, 0xba, 0xbc, 0xbc], 1),
+        Spiny => ([0xfc, 0xfc, 0x96, 0x97, 0x98, 0x99], 2),
+        _ => return None,
+    };
+
+    Some(sprite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metatile, METATILE_COUNTS};
+
+    fn graphics() -> LevelGraphics {
+        // tile n is filled with color n % 4
+        let tiles =
+            (0..=255u8).map(|n| Tile::new([[n % 4; 8]; 8])).collect::<Vec<_>>();
+        let mut palettes = Palettes::default();
+        palettes.background[0] = [0x22, 0x29, 0x1a, 0x0f];
+        palettes.background[1] = [0x22, 0x27, 0x16, 0x0f];
+        palettes.sprites[3] = [0x22, 0x30, 0x0f, 0x36];
+
+        // every metatile is blank, except for the ground
+        let mut metatiles = MetatileTables {
+            tables: [0, 1, 2, 3].map(|palette| {
+                let blank = Metatile::new([0x24; 4], palette as u8);
+                vec![blank; METATILE_COUNTS[palette]]
+            }),
+        };
+        metatiles.tables[1][0x14] = Metatile::new([0xb4, 0xb6, 0xb5, 0xb7], 1);
+
+        LevelGraphics {
+            background: PatternTable { tiles: tiles.clone() },
+            sprites: PatternTable { tiles },
+            palettes,
+            metatiles,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let area_data = [
+            0x10, 0x01, // no scenery, basic floor
+            0xfd,
+        ];
+        let enemy_data = [0xba, 0x06, 0xff]; // goomba
+        let level = Level::from_bytes(&area_data, &enemy_data).unwrap();
+        let graphics = graphics();
+        let master_palette = MasterPalette::default();
+        let rgb = |color| master_palette.rgb(color);
+
+        let image = ImageRenderer::new(
+            &level,
+            AreaType::Ground,
+            &graphics,
+            &master_palette,
+        )
+        .render();
+
+        assert_eq!(image.width, 16 * METATILE_SIZE);
+        assert_eq!(image.height, LEVEL_HEIGHT * METATILE_SIZE);
+        assert_eq!(image.pixels.len(), image.width * image.height);
+
+        // the sky is blank tiles ($24, color 0)
+        assert_eq!(image.pixel(0, 0), rgb(0x22));
+        // the ground is tiles $b4-$b7 (colors 0-3) in palette 1
+        assert_eq!(image.pixel(0, 11 * 16), rgb(0x22));
+        assert_eq!(image.pixel(0, 11 * 16 + 8), rgb(0x16));
+        assert_eq!(image.pixel(8, 11 * 16), rgb(0x27));
+        assert_eq!(image.pixel(8, 11 * 16 + 8), rgb(0x0f));
+
+        // the goomba is tiles $70-$73 (colors 0-3) in sprite palette 3, color
+        // 0 is transparent
+        let (x, y) = (11 * 16, 10 * 16);
+        assert_eq!(image.pixel(x, y), rgb(0x22));
+        assert_eq!(image.pixel(x + 8, y), rgb(0x30));
+        assert_eq!(image.pixel(x, y + 8), rgb(0x0f));
+        assert_eq!(image.pixel(x + 8, y + 8), rgb(0x36));
+    }
+}
@@ -0,0 +1,901 @@
+use crate::{
+    AreaType, Level, LevelGround, LevelObjectKind, LevelPlatform, LevelScenery,
+    MetatileKind,
+};
+
+use MetatileKind::*;
+
+/// Rows of metatiles in a level (the status bar isn't part of the level).
+pub const LEVEL_HEIGHT: usize = 13;
+
+/// Columns of metatiles in a page.
+pub const PAGE_WIDTH: usize = 16;
+
+/**
+ * A level drawn as metatiles, one column per tile X coordinate (see
+ * `LevelPosition::x`) and `LEVEL_HEIGHT` rows, like the game's metatile
+ * buffer.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetatileGrid {
+    pub columns: Vec<[MetatileKind; LEVEL_HEIGHT]>,
+}
+
+impl MetatileGrid {
+    pub fn new(width: usize) -> Self {
+        Self { columns: vec![[Blank; LEVEL_HEIGHT]; width] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn height(&self) -> usize {
+        LEVEL_HEIGHT
+    }
+
+    pub fn get(&self, x: usize, row: usize) -> Option<MetatileKind> {
+        self.columns.get(x)?.get(row).copied()
+    }
+
+    /// Set a metatile, anything outside of the grid is dropped (like objects
+    /// that run past the bottom of the screen in the game).
+    pub fn set(&mut self, x: usize, row: usize, metatile: MetatileKind) {
+        if let Some(cell) =
+            self.columns.get_mut(x).and_then(|column| column.get_mut(row))
+        {
+            *cell = metatile;
+        }
+    }
+
+    /// Get the rows of the grid, top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = Vec<MetatileKind>> + '_ {
+        (0..LEVEL_HEIGHT)
+            .map(|row| self.columns.iter().map(|column| column[row]).collect())
+    }
+}
+
+/**
+ * Draws a level into a `MetatileGrid` the way the game's area parser does:
+ * column by column, the background scenery, foreground and terrain are
+ * drawn from the area attributes first (the level header, changed along the
+ * way by the layout objects) and the objects are drawn on top of that.
+ *
+ * Which metatiles are used for terrain, bricks, blocks and coins depends on
+ * the type of the area, which isn't part of the level data itself.
+ *
+ * The terrain and object shapes follow the game, the background scenery is
+ * an approximation of the game's pattern (which repeats every 3 pages).
+ */
+pub struct LevelRenderer<'a> {
+    level: &'a Level,
+    area_type: AreaType,
+}
+
+/// The area attributes that can change in the middle of a level.
+#[derive(Debug, Clone, Copy)]
+struct AreaAttributes {
+    ground: LevelGround,
+    scenery: LevelScenery,
+    foreground: u8,
+}
+
+impl<'a> LevelRenderer<'a> {
+    pub fn new(level: &'a Level, area_type: AreaType) -> Self {
+        Self { level, area_type }
+    }
+
+    /// Width of the level in columns: every page up to the last page with an
+    /// object or enemy on it.
+    pub fn width(&self) -> usize {
+        let pages = self
+            .level
+            .object_data
+            .positions()
+            .iter()
+            .chain(self.level.enemy_data.positions().iter())
+            .map(|position| position.page as usize + 1)
+            .max()
+            .unwrap_or(1);
+
+        pages * PAGE_WIDTH
+    }
+
+    pub fn render(&self) -> MetatileGrid {
+        let mut grid = MetatileGrid::new(self.width());
+        let header = &self.level.level_header;
+
+        let mut attributes = AreaAttributes {
+            ground: header.ground,
+            scenery: header.scenery,
+            foreground: match header.background.value() {
+                value @ 1..=3 => value,
+                _ => 0,
+            },
+        };
+
+        // attribute changes only take effect from the next column on, the
+        // game draws the terrain of a column before its objects
+        let mut changes: Vec<_> = self
+            .level
+            .object_data
+            .objects
+            .iter()
+            .zip(self.level.object_data.positions())
+            .map(|(object, position)| (position.x() as usize, &object.kind))
+            .collect();
+        changes.sort_by_key(|(x, _)| *x);
+        let mut changes = changes.into_iter().peekable();
+
+        for x in 0..grid.width() {
+            while let Some((_, kind)) = changes.next_if(|(cx, _)| *cx < x) {
+                Self::change_attributes(&mut attributes, kind);
+            }
+
+            self.draw_background(&mut grid, x, &attributes);
+            self.draw_terrain(&mut grid, x, attributes.ground);
+        }
+
+        for (object, position) in self
+            .level
+            .object_data
+            .objects
+            .iter()
+            .zip(self.level.object_data.positions())
+        {
+            let x = position.x() as usize;
+            let row = position.coordinate.row as usize;
+            self.draw_object(&mut grid, x, row, &object.kind);
+        }
+
+        grid
+    }
+
+    /// Apply a layout object: the low nibble is the new terrain and the
+    /// high nibble the new background scenery.
+    fn change_attributes(
+        attributes: &mut AreaAttributes,
+        kind: &LevelObjectKind,
+    ) {
+        use LevelObjectKind::*;
+
+        match kind {
+            LayoutEmpty(scenery)
+            | LayoutFloor1Mddle0Ceiling0(scenery)
+            | LayoutFloor1Mddle0Ceiling1(scenery)
+            | LayoutFloor1Mddle0Ceiling3(scenery)
+            | LayoutFloor1Mddle0Ceiling4(scenery)
+            | LayoutFloor1Mddle0Ceiling8(scenery)
+            | LayoutFloor4Mddle0Ceiling1(scenery)
+            | LayoutFloor4Mddle0Ceiling3(scenery)
+            | LayoutFloor4Mddle0Ceiling4(scenery)
+            | LayoutFloor5Mddle0Ceiling1(scenery)
+            | LayoutFloor0Mddle0Ceiling1(scenery)
+            | LayoutFloor5Mddle0Ceiling4(scenery)
+            | LayoutFloor8Mddle0Ceiling1(scenery)
+            | LayoutFloor1Mddle5Ceiling1(scenery)
+            | LayoutFloor1Mddle4Ceiling1(scenery)
+            | LayoutFull(scenery) => {
+                attributes.ground = LevelGround::new(kind.value() & 0x0f);
+                attributes.scenery = LevelScenery::new(scenery.value());
+            }
+            // the rest of the values only change the background color
+            ChangeBackground(background) => {
+                attributes.foreground = match background.value() {
+                    value @ 0..=3 => value,
+                    _ => 0,
+                };
+            }
+            _ => (),
+        }
+    }
+
+    fn draw_background(
+        &self,
+        grid: &mut MetatileGrid,
+        x: usize,
+        attributes: &AreaAttributes,
+    ) {
+        let stamps = match attributes.scenery {
+            LevelScenery::Nothing => &[][..],
+            LevelScenery::Clouds => CLOUD_SCENERY,
+            LevelScenery::Mountains => MOUNTAIN_SCENERY,
+            LevelScenery::Fence => FENCE_SCENERY,
+        };
+
+        let column = x % SCENERY_WIDTH;
+        for (start, row, rows) in stamps {
+            for (y, metatiles) in rows.iter().enumerate() {
+                let Some(&metatile) = column
+                    .checked_sub(*start)
+                    .and_then(|offset| metatiles.get(offset))
+                else {
+                    continue;
+                };
+                if metatile != Blank {
+                    grid.set(x, row + y, metatile);
+                }
+            }
+        }
+
+        if attributes.foreground != 0 {
+            let foreground =
+                &FOREGROUND_SCENERY[attributes.foreground as usize];
+            for (row, &metatile) in foreground.iter().enumerate() {
+                if metatile != Blank {
+                    grid.set(x, row, metatile);
+                }
+            }
+        }
+    }
+
+    fn draw_terrain(
+        &self,
+        grid: &mut MetatileGrid,
+        x: usize,
+        ground: LevelGround,
+    ) {
+        let [low, mut high] = TERRAIN_RENDER_BITS[ground.value() as usize];
+        if self.cloud_override() {
+            high &= 0b00001000;
+        }
+
+        let bits = (high as u16) << 8 | low as u16;
+        for row in 0..LEVEL_HEIGHT {
+            if bits & 1 << row != 0 {
+                grid.set(x, row, self.terrain_metatile(row));
+            }
+        }
+    }
+
+    fn draw_object(
+        &self,
+        grid: &mut MetatileGrid,
+        x: usize,
+        row: usize,
+        kind: &LevelObjectKind,
+    ) {
+        match *kind {
+            LevelObjectKind::QuestionBlockPowerup => {
+                grid.set(x, row, QuestionBlockPowerup)
+            }
+            LevelObjectKind::QuestionBlockCoin => {
+                grid.set(x, row, QuestionBlockCoin)
+            }
+            LevelObjectKind::HiddenBlockCoin => {
+                grid.set(x, row, HiddenBlockCoin)
+            }
+            LevelObjectKind::HiddenBlockExtraLife => {
+                grid.set(x, row, HiddenBlockExtraLife)
+            }
+            LevelObjectKind::BrickPowerup => {
+                grid.set(x, row, self.item_brick(0))
+            }
+            LevelObjectKind::BrickVine => grid.set(x, row, self.item_brick(1)),
+            LevelObjectKind::BrickStar => grid.set(x, row, self.item_brick(2)),
+            LevelObjectKind::BrickMultiCoinBlock => {
+                grid.set(x, row, self.item_brick(3))
+            }
+            LevelObjectKind::BrickExtraLife => {
+                grid.set(x, row, self.item_brick(4))
+            }
+            LevelObjectKind::SidewaysPipe => {
+                grid.set(x, row, WaterPipeTop);
+                grid.set(x, row + 1, WaterPipeBottom);
+            }
+            LevelObjectKind::UsedBlock => grid.set(x, row, UsedBlock),
+            LevelObjectKind::Spring => {
+                grid.set(x, row, SpringBlank);
+                grid.set(x, row + 1, SpringHalfBrick);
+            }
+            LevelObjectKind::IslandOrCannon(len) if len > 0 => {
+                self.draw_island_or_cannon(grid, x, row, len)
+            }
+            LevelObjectKind::HorizontalBrick(len) => {
+                Self::fill(grid, x, row, len, 1, self.brick());
+            }
+            LevelObjectKind::HorizontalBlock(len) => {
+                Self::fill(grid, x, row, len, 1, self.solid_block());
+            }
+            LevelObjectKind::HorizontalCoin(len) => {
+                Self::fill(grid, x, row, len, 1, self.coin())
+            }
+            LevelObjectKind::VerticalBrick(len) => {
+                Self::fill(grid, x, row, 1, len, self.brick());
+            }
+            LevelObjectKind::VerticalBlock(len) => {
+                Self::fill(grid, x, row, 1, len, self.solid_block());
+            }
+            LevelObjectKind::PipeNoEntry(height) if height > 0 => {
+                Self::draw_pipe(
+                    grid,
+                    x,
+                    row,
+                    height,
+                    [PipeEndLeft, PipeEndRight],
+                );
+            }
+            LevelObjectKind::PipeEntry(height) if height > 0 => {
+                Self::draw_pipe(
+                    grid,
+                    x,
+                    row,
+                    height,
+                    [WarpPipeEndLeft, WarpPipeEndRight],
+                )
+            }
+
+            LevelObjectKind::Hole(len) => Self::fill(grid, x, 8, len, 5, Blank),
+            LevelObjectKind::BalanceHorizontalRope(len) if len > 0 => {
+                Self::fill(grid, x, 0, len, 1, HorizontalRope);
+                grid.set(x, 0, PulleyLeft);
+                grid.set(x + len as usize - 1, 0, PulleyRight);
+            }
+            LevelObjectKind::BridgeY7(len) => {
+                Self::draw_bridge(grid, x, 7, len)
+            }
+            LevelObjectKind::BridgeY8(len) => {
+                Self::draw_bridge(grid, x, 8, len)
+            }
+            LevelObjectKind::BridgeY10(len) => {
+                Self::draw_bridge(grid, x, 10, len)
+            }
+            LevelObjectKind::FilledHole(len) => {
+                Self::fill(grid, x, 10, len, 1, WaterTop);
+                Self::fill(grid, x, 11, len, 2, Water);
+            }
+            LevelObjectKind::HorizontalQuestionBlockY3(len) => {
+                Self::fill(grid, x, 3, len, 1, QuestionBlockCoin);
+            }
+            LevelObjectKind::HorizontalQuestionBlockY7(len) => {
+                Self::fill(grid, x, 7, len, 1, QuestionBlockCoin);
+            }
+
+            LevelObjectKind::ReverseLPipe => Self::draw_l_pipe(grid, x, 0),
+            LevelObjectKind::FlagPole => {
+                grid.set(x, 0, FlagpoleBall);
+                Self::fill(grid, x, 1, 1, 9, FlagpoleShaft);
+                grid.set(x, 10, SolidBlock);
+            }
+            LevelObjectKind::CastleAxe => grid.set(x, 6, Axe),
+            LevelObjectKind::AxeRope => grid.set(x, 7, Chain),
+            LevelObjectKind::CastleBridge => {
+                Self::fill(grid, x, 8, 13, 1, BowserBridge)
+            }
+
+            LevelObjectKind::LiftRope => {
+                Self::fill(grid, x, 0, 1, LEVEL_HEIGHT as u8, VerticalRope)
+            }
+            LevelObjectKind::BalanceLiftVerticalRope(len) => {
+                Self::fill(grid, x, 1, 1, len, VerticalRope);
+            }
+            LevelObjectKind::BigCastle => Self::draw_castle(grid, x, 0),
+            LevelObjectKind::Castle(row) => {
+                Self::draw_castle(grid, x, row as usize)
+            }
+            LevelObjectKind::Staircase(len) => {
+                self.draw_staircase(grid, x, len)
+            }
+            LevelObjectKind::TallReverseLPipe(row) => {
+                Self::draw_l_pipe(grid, x, row as usize)
+            }
+
+            // scroll stops, enemy generators, layout changes (which are drawn
+            // with the terrain), objects with no length and such
+            _ => (),
+        }
+    }
+
+    /// Draw a rectangle of one metatile.
+    fn fill(
+        grid: &mut MetatileGrid,
+        x: usize,
+        row: usize,
+        width: u8,
+        height: u8,
+        metatile: MetatileKind,
+    ) {
+        for x in x..x + width as usize {
+            for row in row..row + height as usize {
+                grid.set(x, row, metatile);
+            }
+        }
+    }
+
+    /// A vertical pipe, 2 columns wide, `height` has to be at least 1.
+    fn draw_pipe(
+        grid: &mut MetatileGrid,
+        x: usize,
+        row: usize,
+        height: u8,
+        [left, right]: [MetatileKind; 2],
+    ) {
+        grid.set(x, row, left);
+        grid.set(x + 1, row, right);
+        Self::fill(grid, x, row + 1, 1, height - 1, PipeShaftLeft);
+        Self::fill(grid, x + 1, row + 1, 1, height - 1, PipeShaftRight);
+    }
+
+    /// A sideways pipe at rows 9 and 10 that joins a vertical pipe going up
+    /// to `top`, 4 columns wide.
+    fn draw_l_pipe(grid: &mut MetatileGrid, x: usize, top: usize) {
+        let height = 9u8.saturating_sub(top as u8);
+
+        grid.set(x, 9, SidewaysPipeEndTop);
+        grid.set(x, 10, SidewaysPipeEndBottom);
+        grid.set(x + 1, 9, SidewaysPipeShaftTop);
+        grid.set(x + 1, 10, SidewaysPipeShaftBottom);
+        grid.set(x + 2, 9, SidewaysPipeJointTop);
+        grid.set(x + 2, 10, SidewaysPipeJointBottom);
+        Self::fill(grid, x + 2, top, 1, height, PipeShaftLeft);
+        Self::fill(grid, x + 3, top, 1, height + 2, PipeShaftRight);
+    }
+
+    /// A bridge with the guardrail on the row above it.
+    fn draw_bridge(grid: &mut MetatileGrid, x: usize, row: usize, len: u8) {
+        Self::fill(grid, x, row - 1, len, 1, BridgeGuardrail);
+        Self::fill(grid, x, row, len, 1, Bridge);
+    }
+
+    /// A castle from `row` down to the floor, the rows that don't fit are cut
+    /// off at the bottom.
+    fn draw_castle(grid: &mut MetatileGrid, x: usize, row: usize) {
+        for (metatiles, row) in CASTLE_METATILES.iter().zip(row..11) {
+            for (offset, &metatile) in metatiles.iter().enumerate() {
+                grid.set(x + offset, row, metatile);
+            }
+        }
+    }
+
+    /// A staircase going up to the right, the steps are the same no matter
+    /// how wide the staircase is.
+    fn draw_staircase(&self, grid: &mut MetatileGrid, x: usize, len: u8) {
+        for (step, &top) in STAIRCASE_ROWS.iter().enumerate().take(len as usize)
+        {
+            Self::fill(
+                grid,
+                x + step,
+                top,
+                1,
+                (11 - top) as u8,
+                self.solid_block(),
+            );
+        }
+    }
+
+    /// Tree ledges, mushroom ledges or bullet bill cannons, depending on the
+    /// platform type of the level.  `len` has to be at least 1.
+    fn draw_island_or_cannon(
+        &self,
+        grid: &mut MetatileGrid,
+        x: usize,
+        row: usize,
+        len: u8,
+    ) {
+        let last = x + len as usize - 1;
+
+        match self.area_style() {
+            LevelPlatform::BulletBills => {
+                grid.set(x, row, CannonBarrel);
+                grid.set(x, row + 1, CannonTop);
+                Self::fill(
+                    grid,
+                    x,
+                    row + 2,
+                    1,
+                    len.saturating_sub(2),
+                    CannonBottom,
+                );
+            }
+            LevelPlatform::OrangeAndMushrooms => {
+                Self::fill(grid, x, row, len, 1, MushroomMiddle);
+                grid.set(x, row, MushroomLeft);
+                grid.set(last, row, MushroomRight);
+
+                let stump = x + len as usize / 2;
+                grid.set(stump, row + 1, MushroomStumpTop);
+                for row in row + 2..LEVEL_HEIGHT {
+                    grid.set(stump, row, MushroomStumpBottom);
+                }
+            }
+            _ => {
+                for x in x..=last {
+                    grid.set(x, row, TreeLedgeMiddle);
+                    for row in row + 1..LEVEL_HEIGHT {
+                        grid.set(x, row, TreeTrunk);
+                    }
+                }
+                grid.set(x, row, TreeLedgeLeft);
+                grid.set(last, row, TreeLedgeRight);
+                for row in row + 1..LEVEL_HEIGHT {
+                    grid.set(x, row, Blank);
+                    grid.set(last, row, Blank);
+                }
+            }
+        }
+    }
+
+    /// Cloud levels use tree ledges with clouds for terrain and bricks.
+    fn cloud_override(&self) -> bool {
+        self.level.level_header.platform == LevelPlatform::Clouds
+    }
+
+    fn area_style(&self) -> LevelPlatform {
+        match self.level.level_header.platform {
+            LevelPlatform::Clouds => LevelPlatform::GreenAndTrees,
+            platform => platform,
+        }
+    }
+
+    fn terrain_metatile(&self, row: usize) -> MetatileKind {
+        if self.cloud_override() {
+            return CloudGround;
+        }
+
+        match self.area_type {
+            AreaType::Water => WaterBlock,
+            AreaType::Ground => Ground,
+            // the floor of underground levels is rock, not bricks
+            AreaType::Underground if row >= 11 => Ground,
+            AreaType::Underground => Brick,
+            AreaType::Castle => CastleBlock,
+        }
+    }
+
+    fn brick(&self) -> MetatileKind {
+        if self.cloud_override() {
+            return CloudGround;
+        }
+
+        match self.area_type {
+            AreaType::Water => Seaplant,
+            AreaType::Ground => BrickLine,
+            AreaType::Underground | AreaType::Castle => Brick,
+        }
+    }
+
+    /// Bricks with an item only have the line on top in ground levels.
+    fn item_brick(&self, item: usize) -> MetatileKind {
+        let bricks = if self.area_type == AreaType::Ground {
+            [
+                BrickLinePowerup,
+                BrickLineVine,
+                BrickLineStar,
+                BrickLineMultiCoin,
+                BrickLineExtraLife,
+            ]
+        } else {
+            [BrickPowerup, BrickVine, BrickStar, BrickMultiCoin, BrickExtraLife]
+        };
+
+        bricks[item]
+    }
+
+    fn solid_block(&self) -> MetatileKind {
+        match self.area_type {
+            AreaType::Water => WaterBlock,
+            AreaType::Ground | AreaType::Underground => SolidBlock,
+            AreaType::Castle => CastleBlock,
+        }
+    }
+
+    fn coin(&self) -> MetatileKind {
+        match self.area_type {
+            AreaType::Water => WaterCoin,
+            _ => Coin,
+        }
+    }
+}
+
+/**
+ * TerrainRenderBits: the rows filled in by each `LevelGround`, bit 0 of the
+ * first byte is the top row and bit 4 of the second byte the bottom row.
+ */
+const TERRAIN_RENDER_BITS: [[u8; 2]; 16] = [
+    [0x00, 0x00],
+    [0x00, 0x18],
+    [0x01, 0x18],
+    [0x07, 0x18],
+    [0x0f, 0x18],
+    [0xff, 0x18],
+    [0x01, 0x1f],
+    [0x07, 0x1f],
+    [0x0f, 0x1f],
+    [0x81, 0x1f],
+    [0x01, 0x00],
+    [0x8f, 0x1f],
+    [0xf1, 0x1f],
+    [0xf9, 0x18],
+    [0xf1, 0x18],
+    [0xff, 0x1f],
+];
+
+/// ForeSceneryData: nothing, in water, castle wall and over water.
+const FOREGROUND_SCENERY: [[MetatileKind; LEVEL_HEIGHT]; 4] = [
+    [Blank; LEVEL_HEIGHT],
+    [
+        WaterTop, Water, Water, Water, Water, Water, Water, Water, Water,
+        Water, Water, WaterBlock, WaterBlock,
+    ],
+    [
+        Blank,
+        Blank,
+        Blank,
+        Blank,
+        Blank,
+        CastleTop,
+        CastleBrickWall,
+        CastleBrickWall,
+        CastleBrickWall,
+        CastleBrickWall,
+        CastleBrickWall,
+        Blank,
+        Blank,
+    ],
+    [
+        Blank, Blank, Blank, Blank, Blank, Blank, Blank, Blank, Blank, Blank,
+        Blank, WaterTop, Water,
+    ],
+];
+
+/// CastleMetatiles: 5 columns wide, drawn from the top down.
+const CASTLE_METATILES: [[MetatileKind; 5]; 11] = [
+    [Blank, CastleTop, CastleTop, CastleTop, Blank],
+    [Blank, CastleWindowRight, CastleBrickWall, CastleWindowLeft, Blank],
+    [CastleTop, CastleTopBrick, CastleTopBrick, CastleTopBrick, CastleTop],
+    [
+        CastleBrickWall,
+        CastleBrickWall,
+        CastleEntranceTop,
+        CastleBrickWall,
+        CastleBrickWall,
+    ],
+    [
+        CastleBrickWall,
+        CastleBrickWall,
+        CastleEntranceBottom,
+        CastleBrickWall,
+        CastleBrickWall,
+    ],
+    [CastleTopBrick; 5],
+    [
+        CastleBrickWall,
+        CastleEntranceTop,
+        CastleBrickWall,
+        CastleEntranceTop,
+        CastleBrickWall,
+    ],
+    [
+        CastleBrickWall,
+        CastleEntranceBottom,
+        CastleBrickWall,
+        CastleEntranceBottom,
+        CastleBrickWall,
+    ],
+    [CastleBrickWall; 5],
+    [
+        CastleEntranceTop,
+        CastleBrickWall,
+        CastleEntranceTop,
+        CastleBrickWall,
+        CastleEntranceTop,
+    ],
+    [
+        CastleEntranceBottom,
+        CastleBrickWall,
+        CastleEntranceBottom,
+        CastleBrickWall,
+        CastleEntranceBottom,
+    ],
+];
+
+/// The top row of each step of a staircase, the steps reach down to row 10.
+const STAIRCASE_ROWS: [usize; 9] = [10, 9, 8, 7, 6, 5, 4, 3, 3];
+
+/// The background scenery repeats every 3 pages.
+const SCENERY_WIDTH: usize = 3 * PAGE_WIDTH;
+
+/// Background scenery: the column and row of the top left corner of some
+/// metatiles, `Blank` is see-through.
+type Stamp = (usize, usize, &'static [&'static [MetatileKind]]);
+
+const CLOUD: &[&[MetatileKind]] = &[
+    &[CloudLeft, CloudMiddle, CloudRight],
+    &[CloudBottomLeft, CloudBottomMiddle, CloudBottomRight],
+];
+
+const DOUBLE_CLOUD: &[&[MetatileKind]] = &[
+    &[CloudLeft, CloudMiddle, CloudMiddle, CloudRight],
+    &[CloudBottomLeft, CloudBottomMiddle, CloudBottomMiddle, CloudBottomRight],
+];
+
+const TRIPLE_CLOUD: &[&[MetatileKind]] = &[
+    &[CloudLeft, CloudMiddle, CloudMiddle, CloudMiddle, CloudRight],
+    &[
+        CloudBottomLeft,
+        CloudBottomMiddle,
+        CloudBottomMiddle,
+        CloudBottomMiddle,
+        CloudBottomRight,
+    ],
+];
+
+const BIG_MOUNTAIN: &[&[MetatileKind]] = &[
+    &[Blank, Blank, MountainTop],
+    &[Blank, MountainLeft, MountainLeftBottom, MountainRight],
+    &[
+        MountainLeft,
+        MountainLeftBottom,
+        MountainMiddleBottom,
+        MountainRightBottom,
+        MountainRight,
+    ],
+];
+
+const SMALL_MOUNTAIN: &[&[MetatileKind]] = &[
+    &[Blank, MountainTop],
+    &[MountainLeft, MountainMiddleBottom, MountainRight],
+];
+
+const BUSH: &[&[MetatileKind]] = &[&[BushLeft, BushMiddle, BushRight]];
+
+const TRIPLE_BUSH: &[&[MetatileKind]] =
+    &[&[BushLeft, BushMiddle, BushMiddle, BushMiddle, BushRight]];
+
+const TALL_TREE: &[&[MetatileKind]] =
+    &[&[TallTreeTop], &[TallTreeBottom], &[TreeTrunk]];
+
+const SHORT_TREE: &[&[MetatileKind]] = &[&[ShortTreeTop], &[TreeTrunk]];
+
+const FENCE: &[&[MetatileKind]] = &[&[Fence, Fence, Fence, Fence]];
+
+const CLOUD_SCENERY: &[Stamp] = &[
+    (3, 2, CLOUD),
+    (9, 1, DOUBLE_CLOUD),
+    (19, 2, TRIPLE_CLOUD),
+    (27, 1, CLOUD),
+    (36, 2, DOUBLE_CLOUD),
+];
+
+const MOUNTAIN_SCENERY: &[Stamp] = &[
+    (0, 8, BIG_MOUNTAIN),
+    (8, 2, CLOUD),
+    (11, 10, TRIPLE_BUSH),
+    (16, 9, SMALL_MOUNTAIN),
+    (19, 1, CLOUD),
+    (23, 10, BUSH),
+    (27, 2, TRIPLE_CLOUD),
+    (36, 1, DOUBLE_CLOUD),
+    (41, 10, BUSH),
+];
+
+const FENCE_SCENERY: &[Stamp] = &[
+    (8, 2, CLOUD),
+    (11, 8, TALL_TREE),
+    (13, 9, SHORT_TREE),
+    (14, 10, FENCE),
+    (19, 1, CLOUD),
+    (27, 2, TRIPLE_CLOUD),
+    (29, 9, SHORT_TREE),
+    (36, 1, DOUBLE_CLOUD),
+    (40, 8, TALL_TREE),
+    (41, 10, FENCE),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LevelObject;
+
+    fn render(bytes: &[u8], area_type: AreaType) -> MetatileGrid {
+        let level = Level::from_bytes(bytes, &[0xff]).unwrap();
+        LevelRenderer::new(&level, area_type).render()
+    }
+
+    #[test]
+    fn test_terrain() {
+        // no scenery, basic floor
+        let grid = render(&[0x10, 0x01, 0xfd], AreaType::Ground);
+
+        assert_eq!(grid.width(), PAGE_WIDTH);
+        for x in 0..grid.width() {
+            assert_eq!(grid.get(x, 10), Some(Blank));
+            assert_eq!(grid.get(x, 11), Some(Ground));
+            assert_eq!(grid.get(x, 12), Some(Ground));
+        }
+
+        // basic floor and ceiling, underground
+        let grid = render(&[0x10, 0x02, 0xfd], AreaType::Underground);
+        assert_eq!(grid.get(0, 0), Some(Brick));
+        assert_eq!(grid.get(0, 1), Some(Blank));
+        assert_eq!(grid.get(0, 11), Some(Ground));
+    }
+
+    #[test]
+    fn test_layout_change() {
+        // basic floor, a hole from column 4 and the floor back on page 1
+        let bytes = [0x10, 0x01, 0x4e, 0x00, 0x0e, 0x81, 0xfd];
+        let grid = render(&bytes, AreaType::Ground);
+
+        assert_eq!(grid.width(), 2 * PAGE_WIDTH);
+        assert_eq!(grid.get(4, 11), Some(Ground));
+        assert_eq!(grid.get(5, 11), Some(Blank));
+        assert_eq!(grid.get(16, 11), Some(Blank));
+        assert_eq!(grid.get(17, 11), Some(Ground));
+    }
+
+    #[test]
+    fn test_objects() {
+        let bytes = [
+            0x10, 0x01, // no scenery, basic floor
+            0x07, 0x01, // ? block (coin)
+            0x27, 0x22, // 3 bricks
+            0x79, 0x71, // pipe, 3 high
+            0xcc, 0x01, // hole, 2 wide
+            0x0f, 0xb8, // staircase, 9 wide, new page
+            0xdf, 0x20, // castle
+            0xfd,
+        ];
+        let grid = render(&bytes, AreaType::Ground);
+
+        assert_eq!(grid.get(0, 7), Some(QuestionBlockCoin));
+        assert_eq!(grid.get(2, 7), Some(BrickLine));
+        assert_eq!(grid.get(4, 7), Some(BrickLine));
+        assert_eq!(grid.get(5, 7), Some(Blank));
+
+        assert_eq!(grid.get(7, 9), Some(PipeEndLeft));
+        assert_eq!(grid.get(8, 9), Some(PipeEndRight));
+        assert_eq!(grid.get(7, 11), Some(PipeShaftLeft));
+        assert_eq!(grid.get(8, 11), Some(PipeShaftRight));
+
+        assert_eq!(grid.get(12, 11), Some(Blank));
+        assert_eq!(grid.get(13, 12), Some(Blank));
+        assert_eq!(grid.get(14, 11), Some(Ground));
+
+        assert_eq!(grid.get(16, 10), Some(SolidBlock));
+        assert_eq!(grid.get(16, 9), Some(Blank));
+        assert_eq!(grid.get(23, 3), Some(SolidBlock));
+        assert_eq!(grid.get(24, 3), Some(SolidBlock));
+
+        assert_eq!(grid.get(30, 0), Some(CastleTop));
+        assert_eq!(grid.get(29, 10), Some(CastleEntranceBottom));
+    }
+
+    #[test]
+    fn test_zero_length_objects() {
+        // levels built without `check_encoding` can have objects with no
+        // length, they aren't drawn
+        let mut level =
+            Level::from_bytes(&[0x10, 0x00, 0x00, 0x00, 0xfd], &[0xff])
+                .unwrap();
+        let object = level.object_data.objects[0].clone();
+        level.object_data.objects = [
+            LevelObjectKind::PipeEntry(0),
+            LevelObjectKind::PipeNoEntry(0),
+            LevelObjectKind::IslandOrCannon(0),
+            LevelObjectKind::BalanceHorizontalRope(0),
+            LevelObjectKind::HorizontalBrick(0),
+        ]
+        .into_iter()
+        .map(|kind| LevelObject { kind, ..object.clone() })
+        .collect();
+
+        let grid = LevelRenderer::new(&level, AreaType::Ground).render();
+        for x in 0..grid.width() {
+            for row in 0..LEVEL_HEIGHT {
+                assert_eq!(grid.get(x, row), Some(Blank));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cloud_override() {
+        // clouds platform, basic floor and ceiling
+        let grid = render(&[0x10, 0xc2, 0x27, 0x20, 0xfd], AreaType::Ground);
+
+        assert_eq!(grid.get(0, 0), Some(CloudGround));
+        assert_eq!(grid.get(0, 11), Some(CloudGround));
+        assert_eq!(grid.get(0, 12), Some(Blank));
+        assert_eq!(grid.get(2, 7), Some(CloudGround));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{ParseError, ParseResult};
+
+/// Number of metatiles the game has for each palette.
+pub const METATILE_COUNTS: [usize; 4] = [39, 46, 10, 6];
+
+/// Metatiles a table can have, the low 6 bits of the id index it.
+const MAX_METATILES: usize = 64;
+
+/// Size of the MetatileGraphics_Low and MetatileGraphics_High tables.
+const POINTER_TABLES_SIZE: usize = 8;
+
+/// CPU address PRG-ROM is mapped at.
+const PRG_IMAGE_START: usize = 0x8000;
+
+/**
+ * The 4 tiles (from the background pattern table) a metatile is drawn with
+ * and the palette it uses.
+ *
+ * The tiles are stored in the order the game draws them: top left, bottom
+ * left, top right, bottom right.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metatile {
+    pub tiles: [u8; 4],
+    pub palette: u8,
+}
+
+impl Metatile {
+    pub fn new(tiles: [u8; 4], palette: u8) -> Self {
+        Self { tiles, palette }
+    }
+
+    /// Get the tile at a position in the metatile, `x` and `y` are 0 or 1.
+    pub fn tile(&self, x: usize, y: usize) -> u8 {
+        self.tiles[x * 2 + y]
+    }
+}
+
+/**
+ * The game's MetatileGraphics tables: one table of metatiles per palette,
+ * indexed by the low 6 bits of the metatile id (the top 2 bits pick the
+ * palette, see `MetatileKind`).
+ *
+ * The game stores the tables right after the pointer tables to them:
+ *
+ * | table                  | size     |
+ * |------------------------|----------|
+ * | MetatileGraphics_Low   | 4        |
+ * | MetatileGraphics_High  | 4        |
+ * | Palette0_MTiles        | 39 * 4   |
+ * | Palette1_MTiles        | 46 * 4   |
+ * | Palette2_MTiles        | 10 * 4   |
+ * | Palette3_MTiles        | 6 * 4    |
+ *
+ * From: https://gist.github.com/1wErt3r/4048722 (MetatileGraphics)
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetatileTables {
+    pub tables: [Vec<Metatile>; 4],
+}
+
+impl MetatileTables {
+    /**
+     * Find the tables in the 32 KB of PRG-ROM the game runs from (mapped at
+     * $8000).  The pointer tables have to be followed by one of their
+     * tables like the game's, just about any code has 8 bytes that could be
+     * pointers.  Tables that were moved or made longer are read from where
+     * the pointers say, see `from_prg_image`.
+     */
+    pub fn find(prg_image: &[u8]) -> Option<Self> {
+        let after_pointers = |idx: usize| {
+            Self::table_starts(prg_image, idx).is_ok_and(|starts| {
+                starts.contains(&(idx + POINTER_TABLES_SIZE))
+            })
+        };
+
+        (0..prg_image.len())
+            .filter(|&idx| after_pointers(idx))
+            .find_map(|idx| Self::from_prg_image(prg_image, idx).ok())
+    }
+
+    /**
+     * Parse the tables through the pointer tables at `offset` in the 32 KB
+     * of PRG-ROM mapped at $8000.
+     *
+     * Each table runs up to the start of the next one (at most 64
+     * metatiles), nothing says where the last one ends so it has as many
+     * metatiles as the game's.
+     */
+    pub fn from_prg_image(
+        prg_image: &[u8],
+        offset: usize,
+    ) -> ParseResult<Self> {
+        let starts = Self::table_starts(prg_image, offset)?;
+
+        let mut tables: [Vec<Metatile>; 4] = Default::default();
+        for (palette, table) in tables.iter_mut().enumerate() {
+            let start = starts[palette];
+            let next = starts.iter().filter(|&&other| other > start).min();
+            let len = match next {
+                Some(next) => ((next - start) / 4).min(MAX_METATILES),
+                None => METATILE_COUNTS[palette],
+            };
+            if len == 0
+                || starts.iter().filter(|&&other| other == start).count() > 1
+            {
+                return Err(ParseError::unknown_value(
+                    "metatile table pointers",
+                    &prg_image[offset..offset + POINTER_TABLES_SIZE],
+                ));
+            }
+
+            let bytes = prg_image
+                .get(start..start + len * 4)
+                .ok_or(ParseError::UnexpectedEof { offset: prg_image.len() })?;
+            *table = bytes
+                .chunks_exact(4)
+                .map(|tiles| {
+                    Metatile::new(
+                        [tiles[0], tiles[1], tiles[2], tiles[3]],
+                        palette as u8,
+                    )
+                })
+                .collect();
+        }
+
+        Ok(Self { tables })
+    }
+
+    /// Get a metatile by its id, like the ones in `MetatileKind`.
+    pub fn get(&self, id: u8) -> Option<&Metatile> {
+        self.tables[id as usize >> 6].get(id as usize & 0x3f)
+    }
+
+    /// Offsets of the tables in the 32 KB of PRG-ROM, from the pointer
+    /// tables at `offset`.
+    fn table_starts(
+        prg_image: &[u8],
+        offset: usize,
+    ) -> ParseResult<[usize; 4]> {
+        let pointers = prg_image
+            .get(offset..offset + POINTER_TABLES_SIZE)
+            .ok_or(ParseError::UnexpectedEof { offset: prg_image.len() })?;
+
+        let mut starts = [0; 4];
+        for (palette, start) in starts.iter_mut().enumerate() {
+            let address =
+                u16::from_le_bytes([pointers[palette], pointers[palette + 4]]);
+            *start = (address as usize)
+                .checked_sub(PRG_IMAGE_START)
+                .filter(|&start| start < prg_image.len())
+                .ok_or(ParseError::InvalidAddress { address })?;
+        }
+
+        Ok(starts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PRG-ROM with the tables at $8010, metatile n of each palette is drawn
+    /// with tiles n..n+4.
+    fn prg_image() -> Vec<u8> {
+        let mut prg = vec![0xff; 0x10];
+        let mut address = 0x8010 + POINTER_TABLES_SIZE;
+        let mut high = vec![];
+        for count in METATILE_COUNTS {
+            prg.push(address as u8);
+            high.push((address >> 8) as u8);
+            address += count * 4;
+        }
+        prg.extend(high);
+        for count in METATILE_COUNTS {
+            prg.extend((0..count as u8).flat_map(|n| [n, n + 1, n + 2, n + 3]));
+        }
+
+        prg
+    }
+
+    #[test]
+    fn test_find() {
+        let prg = prg_image();
+        let tables = MetatileTables::find(&prg).unwrap();
+
+        for (table, count) in tables.tables.iter().zip(METATILE_COUNTS) {
+            assert_eq!(table.len(), count);
+        }
+        assert_eq!(tables.get(0x00), Some(&Metatile::new([0, 1, 2, 3], 0)));
+        assert_eq!(tables.get(0x54), Some(&Metatile::new([20, 21, 22, 23], 1)));
+        assert_eq!(tables.get(0xc5), Some(&Metatile::new([5, 6, 7, 8], 3)));
+        assert_eq!(tables.get(0xc6), None);
+        assert_eq!(tables.get(0x54).unwrap().tile(1, 0), 22);
+
+        // the tables are cut off
+        assert!(MetatileTables::find(&prg[..prg.len() - 1]).is_none());
+        // the pointers don't point right after themselves
+        assert!(MetatileTables::from_prg_image(&prg, 0x0f).is_err());
+    }
+
+    #[test]
+    fn test_moved_tables() {
+        let pointer = |prg: &mut Vec<u8>, palette: usize, address: usize| {
+            prg[0x10 + palette] = address as u8;
+            prg[0x14 + palette] = (address >> 8) as u8;
+        };
+
+        // palette 2's table made 2 metatiles longer
+        let mut prg = prg_image();
+        let palette_3 = 0x18 + (39 + 46 + 10) * 4;
+        prg.splice(palette_3..palette_3, [10, 11, 12, 13, 11, 12, 13, 14]);
+        pointer(&mut prg, 3, 0x8000 + palette_3 + 8);
+
+        let tables = MetatileTables::find(&prg).unwrap();
+        let lens = tables.tables.each_ref().map(|table| table.len());
+        assert_eq!(lens, [39, 46, 12, 6]);
+        assert_eq!(tables.get(0x8b), Some(&Metatile::new([11, 12, 13, 14], 2)));
+        assert_eq!(tables.get(0xc5), Some(&Metatile::new([5, 6, 7, 8], 3)));
+
+        // palette 1's table moved to the end, palette 0's table runs into
+        // where it was
+        let mut prg = prg_image();
+        let end = prg.len();
+        pointer(&mut prg, 1, 0x8000 + end);
+        prg.extend((0..46).flat_map(|n| [n, n + 1, n + 2, n + 3]));
+
+        let tables = MetatileTables::find(&prg).unwrap();
+        let lens = tables.tables.each_ref().map(|table| table.len());
+        assert_eq!(lens, [64, 46, 10, 6]);
+        assert_eq!(tables.get(0x6d), Some(&Metatile::new([45, 46, 47, 48], 1)));
+
+        // the pointer tables away from the tables aren't searched for
+        let mut prg = prg_image();
+        let pointers = prg[0x10..0x18].to_vec();
+        prg[0x10..0x18].fill(0xea);
+        let end = prg.len();
+        prg.extend(pointers);
+
+        assert!(MetatileTables::find(&prg).is_none());
+        let tables = MetatileTables::from_prg_image(&prg, end).unwrap();
+        assert_eq!(tables.get(0xc5), Some(&Metatile::new([5, 6, 7, 8], 3)));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::util::enum_mapped;
+
+/*
+ * The metatiles (2x2 tiles) the game builds levels out of, using the ids
+ * from the game's metatile tables.
+ *
+ * The top two bits of the id pick the palette, so the ids are grouped in 4
+ * ranges starting at $00, $40, $80 and $c0.
+ *
+ * From: https://gist.github.com/1wErt3r/4048722 (MetatileGraphics)
+ */
+enum_mapped!(
+    pub MetatileKind (u8) {
+        0x00 => Blank,
+        0x01 => Black,
+        0x02 => BushLeft,
+        0x03 => BushMiddle,
+        0x04 => BushRight,
+        0x05 => MountainLeft,
+        0x06 => MountainLeftBottom,
+        0x07 => MountainTop,
+        0x08 => MountainRight,
+        0x09 => MountainRightBottom,
+        0x0a => MountainMiddleBottom,
+        0x0b => BridgeGuardrail,
+        0x0c => Chain,
+        0x0d => TallTreeTop,
+        0x0e => ShortTreeTop,
+        0x0f => TallTreeBottom,
+        0x10 => WarpPipeEndLeft,
+        0x11 => WarpPipeEndRight,
+        0x12 => PipeEndLeft,
+        0x13 => PipeEndRight,
+        0x14 => PipeShaftLeft,
+        0x15 => PipeShaftRight,
+        0x16 => TreeLedgeLeft,
+        0x17 => TreeLedgeMiddle,
+        0x18 => TreeLedgeRight,
+        0x19 => MushroomLeft,
+        0x1a => MushroomMiddle,
+        0x1b => MushroomRight,
+        0x1c => SidewaysPipeEndTop,
+        0x1d => SidewaysPipeShaftTop,
+        0x1e => SidewaysPipeJointTop,
+        0x1f => SidewaysPipeEndBottom,
+        0x20 => SidewaysPipeShaftBottom,
+        0x21 => SidewaysPipeJointBottom,
+        0x22 => Seaplant,
+        0x23 => BlankHit,
+        0x24 => FlagpoleBall,
+        0x25 => FlagpoleShaft,
+        0x26 => BlankVine,
+
+        0x40 => VerticalRope,
+        0x41 => HorizontalRope,
+        0x42 => PulleyLeft,
+        0x43 => PulleyRight,
+        0x44 => BlankBalanceRope,
+        0x45 => CastleTop,
+        0x46 => CastleWindowLeft,
+        0x47 => CastleBrickWall,
+        0x48 => CastleWindowRight,
+        0x49 => CastleTopBrick,
+        0x4a => CastleEntranceTop,
+        0x4b => CastleEntranceBottom,
+        0x4c => LedgeStump,
+        0x4d => Fence,
+        0x4e => TreeTrunk,
+        0x4f => MushroomStumpTop,
+        0x50 => MushroomStumpBottom,
+        0x51 => BrickLine,
+        0x52 => Brick,
+        0x53 => BrickUnused,
+        0x54 => Ground,
+        0x55 => BrickLinePowerup,
+        0x56 => BrickLineVine,
+        0x57 => BrickLineStar,
+        0x58 => BrickLineMultiCoin,
+        0x59 => BrickLineExtraLife,
+        0x5a => BrickPowerup,
+        0x5b => BrickVine,
+        0x5c => BrickStar,
+        0x5d => BrickMultiCoin,
+        0x5e => BrickExtraLife,
+        0x5f => HiddenBlockCoin,
+        0x60 => HiddenBlockExtraLife,
+        0x61 => SolidBlock,
+        0x62 => CastleBlock,
+        0x63 => Bridge,
+        0x64 => CannonBarrel,
+        0x65 => CannonTop,
+        0x66 => CannonBottom,
+        0x67 => SpringBlank,
+        0x68 => SpringHalfBrick,
+        0x69 => WaterBlock,
+        0x6a => HalfBrick,
+        0x6b => WaterPipeTop,
+        0x6c => WaterPipeBottom,
+        0x6d => FlagBall,
+
+        0x80 => CloudLeft,
+        0x81 => CloudMiddle,
+        0x82 => CloudRight,
+        0x83 => CloudBottomLeft,
+        0x84 => CloudBottomMiddle,
+        0x85 => CloudBottomRight,
+        0x86 => WaterTop,
+        0x87 => Water,
+        0x88 => CloudGround,
+        0x89 => BowserBridge,
+
+        0xc0 => QuestionBlockCoin,
+        0xc1 => QuestionBlockPowerup,
+        0xc2 => Coin,
+        0xc3 => WaterCoin,
+        0xc4 => UsedBlock,
+        0xc5 => Axe,
+    }
+);
+
+impl MetatileKind {
+    /// The palette (0-3) the game draws this metatile with.
+    pub fn palette(&self) -> u8 {
+        self.value() >> 6
+    }
+}
@@ -1,60 +1,778 @@
-use anyhow::{ensure, Result};
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
 
 use crate::*;
 
+mod chr;
+mod identify;
+mod ines;
 mod levels;
+mod space;
 
+pub use chr::*;
+pub use identify::*;
+pub use ines::*;
 pub use levels::*;
+pub use space::*;
+
+pub type Offset = usize;
 
-const ROM_SIZE_BYTES: usize = 40976;
-const ROM_MD5_BYTES: &str = "811b027eaf99c2def7b933c5208636de";
+const NUM_WORLDS: u8 = 8;
+const NUM_AREA_ADDR_OFFSETS: u8 = 36;
+const NUM_AREAS: u8 = 34;
 
 #[derive(Debug)]
 pub struct Rom {
     pub rom_data: Vec<u8>,
+    pub header: INesHeader,
+    pub identity: RomIdentity,
 }
 
 impl Rom {
+    /**
+     * Load a known good dump of the game (headerless dumps are accepted and
+     * get an iNES header added).
+     */
     pub fn new(rom_data: Vec<u8>) -> Result<Self> {
-        Self::validate_rom_data(&rom_data)?;
-        let rom = Self { rom_data };
+        let rom = Self::new_unverified(rom_data)?;
+        ensure!(
+            rom.identity.verified,
+            "unknown rom file (md5 {}), only known dumps are accepted unless \
+             loaded as unverified",
+            rom.identity.md5
+        );
+
         Ok(rom)
     }
 
-    pub fn get_level(&self, level_name: &RomLevel) -> Level {
+    /**
+     * Load any rom laid out like the game, such as rom hacks or dumps that
+     * aren't known.  The level data is found through the rom's own pointer
+     * tables, so it is only as good as the rom it came from.
+     */
+    pub fn new_unverified(rom_data: Vec<u8>) -> Result<Self> {
+        let rom_data = RomIdentity::normalize(rom_data);
+        let header = INesHeader::from_bytes(&rom_data)?;
+        let identity = RomIdentity::identify(&rom_data, &header)?;
+
+        Ok(Self { rom_data, header, identity })
+    }
+
+    /// All of PRG-ROM, for multicarts this includes the other games.
+    pub fn prg_rom(&self) -> &[u8] {
+        &self.rom_data[self.header.prg_rom_range()]
+    }
+
+    /// All of CHR-ROM.
+    pub fn chr_rom(&self) -> &[u8] {
+        &self.rom_data[self.header.chr_rom_range()]
+    }
+
+    /// Find the palette data the game loads into the PPU.
+    pub fn palette_tables(&self) -> Result<PaletteTables> {
+        PaletteTables::find(self.prg_rom())
+            .context("could not find the palette data in the rom")
+    }
+
+    /// Get the palettes the game uses for a level in an area.
+    pub fn level_palettes(
+        &self,
+        area: &Area,
+        level: &Level,
+    ) -> Result<Palettes> {
+        Ok(self
+            .palette_tables()?
+            .level_palettes(area.area_type, &level.level_header))
+    }
+
+    /// Get the tiles and palettes the game draws a level in an area with.
+    pub fn level_graphics(
+        &self,
+        area: &Area,
+        level: &Level,
+    ) -> Result<LevelGraphics> {
+        Ok(LevelGraphics {
+            background: self.pattern_table(PatternTableKind::Background)?,
+            sprites: self.pattern_table(PatternTableKind::Sprites)?,
+            palettes: self.level_palettes(area, level)?,
+            metatiles: self.metatile_tables()?,
+        })
+    }
+
+    /// Find the game's metatile definitions.
+    pub fn metatile_tables(&self) -> Result<MetatileTables> {
+        let start = self.cpu_address_to_offset(0x8000);
+        let prg_image =
+            self.rom_data.get(start..start + 0x8000).unwrap_or_default();
+
+        MetatileTables::find(prg_image)
+            .context("could not find the metatile data in the rom")
+    }
+
+    /// Get a pattern table from the game's CHR-ROM.
+    pub fn pattern_table(
+        &self,
+        kind: PatternTableKind,
+    ) -> Result<PatternTable> {
+        let range = self.pattern_table_range(kind)?;
+        Ok(self.get_data(range.start, PatternTable::from_bytes)?)
+    }
+
+    /// Write a whole pattern table into the game's CHR-ROM.
+    pub fn set_pattern_table(
+        &mut self,
+        kind: PatternTableKind,
+        table: &PatternTable,
+    ) -> Result<()> {
+        let range = self.pattern_table_range(kind)?;
+        let bytes = table.to_bytes();
+        ensure!(
+            bytes.len() == range.len(),
+            "pattern table has {} tiles, expected {}",
+            table.tiles.len(),
+            PATTERN_TABLE_TILES
+        );
+
+        self.rom_data[range].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    pub fn get_tile(&self, kind: PatternTableKind, index: u8) -> Result<Tile> {
+        let offset =
+            self.pattern_table_range(kind)?.start + index as usize * TILE_SIZE;
+        Ok(self.get_data(offset, Tile::from_bytes)?)
+    }
+
+    /// Encode a tile and write it into the game's CHR-ROM.
+    pub fn set_tile(
+        &mut self,
+        kind: PatternTableKind,
+        index: u8,
+        tile: &Tile,
+    ) -> Result<()> {
+        let offset =
+            self.pattern_table_range(kind)?.start + index as usize * TILE_SIZE;
+        self.rom_data[offset..offset + TILE_SIZE]
+            .copy_from_slice(&tile.to_bytes());
+
+        Ok(())
+    }
+
+    /**
+     * Convert a CPU address (like $9CB4 in the disassembly) into an offset in
+     * the rom file, using the PRG-ROM bank the game was found in.
+     */
+    pub fn cpu_address_to_offset(&self, address: u16) -> Offset {
+        self.identity.profile.cpu_address_to_offset(address)
+    }
+
+    /// Convert an offset in the rom file into a CPU address.
+    pub fn offset_to_cpu_address(&self, offset: Offset) -> Option<u16> {
+        self.identity.profile.offset_to_cpu_address(offset)
+    }
+
+    pub fn get_level(&self, level_name: &RomLevel) -> Result<Level> {
+        self.get_area(&self.get_level_area(level_name)?)
+    }
+
+    /**
+     * Get the area used for a given level.
+     *
+     * Each world has a list of area pointers, one per area the player
+     * progresses through.  Some levels (1-2, 2-2, 4-2, 7-2) are preceded by
+     * a short cutscene area where the player auto-walks into a pipe, which
+     * takes up a slot in the list without being a level of its own, so those
+     * are skipped.
+     */
+    pub fn get_level_area(&self, level_name: &RomLevel) -> Result<Area> {
+        let profile = &self.identity.profile;
+        let world = level_name.world();
+        let start =
+            self.read_byte(profile.world_addr_offsets() + world as u16)?;
+        let end = if world + 1 < NUM_WORLDS {
+            self.read_byte(profile.world_addr_offsets() + world as u16 + 1)?
+        } else {
+            NUM_AREA_ADDR_OFFSETS
+        };
+
+        let mut level = 0;
+        for area_number in start..end {
+            let byte = self
+                .read_byte(profile.area_addr_offsets() + area_number as u16)?;
+            let area = Area::from_byte(byte);
+
+            let (header_offset, _, _) = self.get_area_offsets(&area)?;
+            let header =
+                self.get_data(header_offset, LevelHeader::from_bytes)?;
+            if header.start_autowalk {
+                continue;
+            }
+
+            if level == level_name.level() {
+                return Ok(area);
+            }
+            level += 1;
+        }
+
+        bail!("no area found for level: {:?}", level_name)
+    }
+
+    /// Get the level data for any area in the game.
+    pub fn get_area(&self, area: &Area) -> Result<Level> {
         let (header_offset, block_offset, enemy_offset) =
-            level_name.get_offsets();
+            self.get_area_offsets(area)?;
 
-        let header_bytes = &self.rom_data[header_offset..];
-        let block_bytes = &self.rom_data[block_offset..];
-        let enemy_bytes = &self.rom_data[enemy_offset..];
+        self.get_level_at(header_offset, block_offset, enemy_offset)
+    }
+
+    /// Encode a level and write it into the rom in place of a given level.
+    pub fn set_level(
+        &mut self,
+        level_name: &RomLevel,
+        level: &Level,
+    ) -> Result<()> {
+        let area = self.get_level_area(level_name)?;
+        self.set_area(&area, level)
+    }
 
-        let level_header = LevelHeader::from_bytes(header_bytes);
-        let object_data = LevelObjectData::from_bytes(block_bytes);
-        let enemy_data = LevelEnemyData::from_bytes(enemy_bytes);
+    /**
+     * Encode a level and write it into the rom in place of a given area.
+     *
+     * The area and enemy data are written over the area's current data, so
+     * the new data must fit in the area's slot: its current data and the
+     * padding after it (see `SpaceReport::slot`).  Anything the new data
+     * doesn't use is padded, so a level that shrinks can grow back later.
+     * Areas can be used by more than one level (5-3 reuses 1-3 for
+     * example), all of them will see the change.
+     */
+    pub fn set_area(&mut self, area: &Area, level: &Level) -> Result<()> {
+        let (area_slot, enemy_slot) = self.area_slots(area)?;
+        let (area_data, enemy_data) = level.to_bytes();
 
-        Level { level_header, object_data, enemy_data }
+        ensure!(
+            area_data.len() <= area_slot.len(),
+            "area data for {} too large: {} > {} bytes",
+            area,
+            area_data.len(),
+            area_slot.len()
+        );
+        ensure!(
+            enemy_data.len() <= enemy_slot.len(),
+            "enemy data for {} too large: {} > {} bytes",
+            area,
+            enemy_data.len(),
+            enemy_slot.len()
+        );
+
+        for (slot, data) in [(area_slot, area_data), (enemy_slot, enemy_data)] {
+            self.rom_data[slot.clone()].fill(PADDING_BYTE);
+            self.rom_data[slot.start..slot.start + data.len()]
+                .copy_from_slice(&data);
+        }
+
+        Ok(())
+    }
+
+    /// Like `set_level`, making room for levels that are larger than their
+    /// current data, see `set_area_repacked`.
+    pub fn set_level_repacked(
+        &mut self,
+        level_name: &RomLevel,
+        level: &Level,
+    ) -> Result<()> {
+        let area = self.get_level_area(level_name)?;
+        self.set_area_repacked(&area, level)
     }
 
-    fn validate_rom_data(data: &[u8]) -> Result<()> {
-        // check rom length
-        let len = data.len();
+    /**
+     * Encode a level and write it into the rom in place of a given area,
+     * making room for it if it's larger than the area's slot.
+     *
+     * A level that fits in the area's slot is written in place like
+     * `set_area`, anything larger (or data shared with other areas) has all
+     * of the level data repacked around it with `repack`.  Unlike
+     * `set_area`, areas that share data with this one keep their data.
+     */
+    pub fn set_area_repacked(
+        &mut self,
+        area: &Area,
+        level: &Level,
+    ) -> Result<()> {
+        let report = self.space_report()?;
+        let shared = report.regions().into_iter().any(|region| {
+            region.shared().any(|block| block.areas.contains(area))
+        });
+        let (area_slot, enemy_slot) = self.area_slots(area)?;
+        let (area_data, enemy_data) = level.to_bytes();
+        if !shared
+            && area_data.len() <= area_slot.len()
+            && enemy_data.len() <= enemy_slot.len()
+        {
+            return self.set_area(area, level);
+        }
+
+        self.repack(&[(*area, level.clone())])
+    }
+
+    /**
+     * Pack the object and enemy data of every area back to back, with the
+     * levels in `edits` written in place of their areas' data.
+     *
+     * The data stays in the regions found by `space_report` and in the order
+     * it was stored, so everything before the first area that changed size
+     * stays where it was.  Only the areas' data and unused ranges that are
+     * all padding are written to, anything else in a region (like code or
+     * tables a hack keeps between the levels) is packed around.  The area
+     * and enemy data address tables are rewritten to point at the new data
+     * and the rest of the room is filled with padding.
+     *
+     * Areas that shared data still share it, except for edited areas that
+     * now have different data, they get their own copy.
+     *
+     * Edited areas have to be in the rom's level data already.  Nothing is
+     * written unless all of the data fits, and every area is read
+     * back afterwards to make sure it still resolves.
+     */
+    pub fn repack(&mut self, edits: &[(Area, Level)]) -> Result<()> {
+        let report = self.space_report()?;
+        let (objects, enemies) = (&report.objects.range, &report.enemies.range);
         ensure!(
-            len == ROM_SIZE_BYTES,
-            "rom size invalid: {} != {}",
-            len,
-            ROM_SIZE_BYTES
+            objects.end <= enemies.start || enemies.end <= objects.start,
+            "object data and enemy data overlap, they can't be repacked"
         );
 
-        // check md5
-        let digest = md5::compute(data);
+        // every block of data, in its new place
+        let mut blocks = vec![];
+        let mut rooms = vec![];
+        for region in report.regions() {
+            let data = |level: &Level| {
+                let (area_data, enemy_data) = level.to_bytes();
+                match region.kind {
+                    LevelDataKind::Objects => area_data,
+                    LevelDataKind::Enemies => enemy_data,
+                }
+            };
+
+            if let Some((area, _)) = edits.iter().find(|(area, _)| {
+                !region.blocks.iter().any(|block| block.areas.contains(area))
+            }) {
+                bail!("{} has no {} to repack", area, region.kind);
+            }
+
+            // edited areas with new data are split off from the block
+            let mut region_blocks = vec![];
+            for block in &region.blocks {
+                let old_data = data(&self.get_area(&block.areas[0])?);
+                let mut areas = vec![];
+                let mut split = vec![];
+                for area in &block.areas {
+                    match edits.iter().find(|(edited, _)| edited == area) {
+                        Some((_, level)) if data(level) != old_data => {
+                            split.push((vec![*area], data(level)))
+                        }
+                        _ => areas.push(*area),
+                    }
+                }
+                if !areas.is_empty() {
+                    region_blocks.push((areas, old_data));
+                }
+                region_blocks.extend(split);
+            }
+
+            let room = Self::repack_room(&self.rom_data, region);
+            let available: usize = room.iter().map(|range| range.len()).sum();
+            let needed: usize =
+                region_blocks.iter().map(|(_, data)| data.len()).sum();
+            let mut free = room.iter().cloned();
+            let mut current = free.next().unwrap_or_default();
+            for (areas, data) in region_blocks {
+                while current.len() < data.len() {
+                    let Some(next) = free.next() else {
+                        bail!(
+                            "{} doesn't fit: {} bytes needed, {} bytes \
+                             available",
+                            region.kind,
+                            needed,
+                            available
+                        );
+                    };
+                    current = next;
+                }
+
+                let len = data.len();
+                blocks.push((region.kind, areas, current.start, data));
+                current.start += len;
+            }
+            rooms.extend(room);
+        }
+
+        // work out where the pointers go before anything is written
+        let mut pointers = vec![];
+        for (kind, areas, offset, _) in &blocks {
+            let address = self
+                .offset_to_cpu_address(*offset)
+                .context("level data is outside of PRG-ROM")?;
+            for area in areas {
+                let (low, high) = self.data_pointer_offsets(*kind, area)?;
+                pointers.push((low, high, address));
+            }
+        }
+
+        let old_rom_data = self.rom_data.clone();
+        for room in rooms {
+            self.rom_data[room].fill(PADDING_BYTE);
+        }
+        for (_, _, offset, data) in &blocks {
+            self.rom_data[*offset..*offset + data.len()].copy_from_slice(data);
+        }
+        for (low, high, address) in pointers {
+            let [low_byte, high_byte] = address.to_le_bytes();
+            self.rom_data[low] = low_byte;
+            self.rom_data[high] = high_byte;
+        }
+
+        // every area has to read back as the data that was packed for it
+        for (kind, areas, _, data) in &blocks {
+            for area in areas {
+                let repacked =
+                    self.get_area(area).map(|level| level.to_bytes());
+                let matches = match (kind, repacked) {
+                    (LevelDataKind::Objects, Ok((area_data, _))) => {
+                        area_data == *data
+                    }
+                    (LevelDataKind::Enemies, Ok((_, enemy_data))) => {
+                        enemy_data == *data
+                    }
+                    (_, Err(_)) => false,
+                };
+                if !matches {
+                    self.rom_data = old_rom_data;
+                    bail!("{} doesn't read back after repacking", area);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The ranges of a region `repack` can write to: the areas' data and the
+    /// unused ranges that are nothing but padding, in order.
+    fn repack_room(rom_data: &[u8], region: &DataRegion) -> Vec<Range<Offset>> {
+        let padding = region.unused.iter().filter(|range| {
+            rom_data[(*range).clone()].iter().all(|&byte| byte == PADDING_BYTE)
+        });
+        let mut ranges: Vec<Range<Offset>> = region
+            .blocks
+            .iter()
+            .map(|block| block.range.clone())
+            .chain(padding.cloned())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        let mut room: Vec<Range<Offset>> = vec![];
+        for range in ranges {
+            match room.last_mut() {
+                Some(last) if range.start <= last.end => {
+                    last.end = last.end.max(range.end)
+                }
+                _ => room.push(range),
+            }
+        }
+
+        room
+    }
+
+    /// Write the rom out as an iNES (.nes) file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let header = INesHeader::from_bytes(&self.rom_data)?;
         ensure!(
-            format!("{:x}", digest) == ROM_MD5_BYTES,
-            "md5 mismatch for rom file: expected {:?} got {:?}",
-            ROM_MD5_BYTES,
-            digest
+            self.rom_data.len() == header.file_size(),
+            "rom data is {} bytes, header expects {} bytes",
+            self.rom_data.len(),
+            header.file_size()
         );
+        fs::write(path, &self.rom_data)?;
 
         Ok(())
     }
+
+    /**
+     * Apply an IPS patch, giving a new rom.  Patched roms are usually hacks,
+     * so it's identified again as unverified and its levels have to parse.
+     */
+    pub fn apply_ips(&self, patch: &IpsPatch) -> Result<Rom> {
+        let rom = Self::new_unverified(patch.apply(&self.rom_data))
+            .context("patched rom isn't valid")?;
+        rom.check_areas().context("patched rom has broken levels")?;
+
+        Ok(rom)
+    }
+
+    /// Create an IPS patch that turns this rom into `edited`.
+    pub fn create_ips(&self, edited: &Rom) -> Result<IpsPatch> {
+        IpsPatch::create(&self.rom_data, &edited.rom_data)
+    }
+
+    /**
+     * Apply a BPS patch, giving a new rom.  The patch's checksums make sure
+     * it's only applied to the rom it was made for, then the result is
+     * checked like `apply_ips`.
+     */
+    pub fn apply_bps(&self, patch: &BpsPatch) -> Result<Rom> {
+        let rom_data =
+            patch.apply(&self.rom_data).context("could not apply BPS patch")?;
+        let rom = Self::new_unverified(rom_data)
+            .context("patched rom isn't valid")?;
+        rom.check_areas().context("patched rom has broken levels")?;
+
+        Ok(rom)
+    }
+
+    /// Create a BPS patch that turns this rom into `edited`.
+    pub fn create_bps(&self, edited: &Rom) -> BpsPatch {
+        BpsPatch::create(&self.rom_data, &edited.rom_data)
+    }
+
+    /// Make sure every area's level data can be read.
+    fn check_areas(&self) -> Result<()> {
+        for area in self.areas()? {
+            self.get_area(&area)
+                .with_context(|| format!("could not read {}", area))?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the level a pipe pointer leads to.
+    pub fn get_pipe_destination(
+        &self,
+        pipe_pointer: &PipePointer,
+    ) -> Result<Level> {
+        self.get_area(&pipe_pointer.area)
+    }
+
+    /**
+     * Get every area in the rom.
+     *
+     * The address tables hold the areas of each type back to back, so the
+     * number of areas for a type is the distance from its base index to the
+     * next base index (or to the end of the table).
+     */
+    pub fn areas(&self) -> Result<Vec<Area>> {
+        let area_data_h_offsets = self.identity.profile.area_data_h_offsets();
+        let bases = AreaType::all()
+            .iter()
+            .map(|area_type| {
+                self.read_byte(area_data_h_offsets + area_type.value() as u16)
+            })
+            .collect::<ParseResult<Vec<u8>>>()?;
+
+        let mut areas = vec![];
+        for (area_type, base) in AreaType::all().into_iter().zip(&bases) {
+            let end = bases
+                .iter()
+                .filter(|other| *other > base)
+                .min()
+                .copied()
+                .unwrap_or(NUM_AREAS);
+
+            for index in 0..end.saturating_sub(*base) {
+                areas.push(Area::new(area_type, index));
+            }
+        }
+
+        Ok(areas)
+    }
+
+    /**
+     * Map out where the level data is and how much room is left for it.
+     *
+     * Each area's data runs from where its pointer points to its terminator
+     * (0xFD for objects, 0xFF for enemies).  Areas that point at the same
+     * data share it, and anything in between areas or in the padding after
+     * the last one is free.
+     */
+    pub fn space_report(&self) -> Result<SpaceReport> {
+        let mut objects = vec![];
+        let mut enemies = vec![];
+        for area in self.areas()? {
+            let (header_offset, _, enemy_offset) =
+                self.get_area_offsets(&area)?;
+            let (area_data, enemy_data) = self
+                .get_area(&area)
+                .with_context(|| format!("could not read {}", area))?
+                .to_bytes();
+
+            let area_end = header_offset + area_data.len();
+            let enemy_end = enemy_offset + enemy_data.len();
+            objects.push((area, header_offset..area_end));
+            enemies.push((area, enemy_offset..enemy_end));
+        }
+
+        let end = self.cpu_address_to_offset(0xffff) + 1;
+        Ok(SpaceReport::new(&self.rom_data, &objects, &enemies, end))
+    }
+
+    /// The room an area's object data and enemy data can use in place.
+    fn area_slots(
+        &self,
+        area: &Area,
+    ) -> Result<(Range<Offset>, Range<Offset>)> {
+        let (header_offset, _, enemy_offset) = self.get_area_offsets(area)?;
+        let report = self.space_report()?;
+
+        Ok((
+            report.slot(LevelDataKind::Objects, header_offset, &self.rom_data),
+            report.slot(LevelDataKind::Enemies, enemy_offset, &self.rom_data),
+        ))
+    }
+
+    /**
+     * Get the header, object, and enemy offsets for a given area by walking
+     * the same pointer tables the game uses: the area type selects a base
+     * index into the address tables and the area index is added to it.
+     */
+    pub fn get_area_offsets(
+        &self,
+        area: &Area,
+    ) -> Result<(Offset, Offset, Offset)> {
+        let profile = &self.identity.profile;
+        let (enemy_idx, area_idx) = self.area_pointer_indexes(area)?;
+
+        let enemy_address = self.read_address(
+            profile.enemy_data_addr_low(),
+            profile.enemy_data_addr_high(),
+            enemy_idx,
+        )?;
+        let area_address = self.read_address(
+            profile.area_data_addr_low(),
+            profile.area_data_addr_high(),
+            area_idx,
+        )?;
+
+        let header_offset =
+            profile.checked_cpu_address_to_offset(area_address)?;
+        let enemy_offset =
+            profile.checked_cpu_address_to_offset(enemy_address)?;
+
+        Ok((header_offset, header_offset + 2, enemy_offset))
+    }
+
+    /// Indexes of an area's pointers in the enemy and area data address
+    /// tables.
+    fn area_pointer_indexes(&self, area: &Area) -> ParseResult<(u8, u8)> {
+        let profile = &self.identity.profile;
+        let area_type = area.area_type.value() as u16;
+
+        let enemy_base =
+            self.read_byte(profile.enemy_addr_h_offsets() + area_type)?;
+        let area_base =
+            self.read_byte(profile.area_data_h_offsets() + area_type)?;
+
+        Ok((
+            enemy_base.wrapping_add(area.index),
+            area_base.wrapping_add(area.index),
+        ))
+    }
+
+    /// File offsets of the low and high bytes of an area's pointer to one
+    /// kind of level data.
+    fn data_pointer_offsets(
+        &self,
+        kind: LevelDataKind,
+        area: &Area,
+    ) -> ParseResult<(Offset, Offset)> {
+        let profile = &self.identity.profile;
+        let (enemy_idx, area_idx) = self.area_pointer_indexes(area)?;
+        let (low_table, high_table, idx) = match kind {
+            LevelDataKind::Objects => (
+                profile.area_data_addr_low(),
+                profile.area_data_addr_high(),
+                area_idx,
+            ),
+            LevelDataKind::Enemies => (
+                profile.enemy_data_addr_low(),
+                profile.enemy_data_addr_high(),
+                enemy_idx,
+            ),
+        };
+
+        Ok((
+            profile.checked_cpu_address_to_offset(low_table + idx as u16)?,
+            profile.checked_cpu_address_to_offset(high_table + idx as u16)?,
+        ))
+    }
+
+    fn get_level_at(
+        &self,
+        header_offset: Offset,
+        block_offset: Offset,
+        enemy_offset: Offset,
+    ) -> Result<Level> {
+        let level_header =
+            self.get_data(header_offset, LevelHeader::from_bytes)?;
+        let object_data =
+            self.get_data(block_offset, LevelObjectData::from_bytes)?;
+        let enemy_data =
+            self.get_data(enemy_offset, LevelEnemyData::from_bytes)?;
+
+        Ok(Level { level_header, object_data, enemy_data })
+    }
+
+    /**
+     * File offsets of a pattern table.  Multicarts have the graphics of
+     * every game in CHR-ROM, the game's are in the bank matching its
+     * PRG-ROM bank.
+     */
+    fn pattern_table_range(
+        &self,
+        kind: PatternTableKind,
+    ) -> Result<Range<Offset>> {
+        let chr_rom = self.header.chr_rom_range();
+        let start = chr_rom.start
+            + self.identity.profile.bank(&self.header) * CHR_SIZE
+            + kind.offset();
+        ensure!(
+            start + PATTERN_TABLE_SIZE <= chr_rom.end,
+            "rom has no CHR-ROM for the game ({} bytes of CHR-ROM)",
+            chr_rom.len()
+        );
+
+        Ok(start..start + PATTERN_TABLE_SIZE)
+    }
+
+    /// Parse data starting at an offset, errors point into the rom file.
+    fn get_data<T>(
+        &self,
+        offset: Offset,
+        parse: fn(&[u8]) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        let bytes = self.rom_data.get(offset..).unwrap_or_default();
+        parse(bytes).map_err(|err| err.offset_by(offset))
+    }
+
+    /// Read a byte from PRG-ROM at the given CPU address.
+    fn read_byte(&self, address: u16) -> ParseResult<u8> {
+        let offset =
+            self.identity.profile.checked_cpu_address_to_offset(address)?;
+        self.rom_data
+            .get(offset)
+            .copied()
+            .ok_or(ParseError::UnexpectedEof { offset })
+    }
+
+    /// Read an address split across a low byte table and a high byte table.
+    fn read_address(
+        &self,
+        low_table: u16,
+        high_table: u16,
+        idx: u8,
+    ) -> ParseResult<u16> {
+        let low = self.read_byte(low_table + idx as u16)?;
+        let high = self.read_byte(high_table + idx as u16)?;
+        Ok(u16::from_le_bytes([low, high]))
+    }
 }
@@ -0,0 +1,152 @@
+use crate::util::enum_mapped;
+use crate::{ParseError, ParseResult};
+
+/// Size of one 8x8 tile in CHR-ROM.
+pub const TILE_SIZE: usize = 16;
+/// Number of tiles in a pattern table.
+pub const PATTERN_TABLE_TILES: usize = 256;
+/// Size of a pattern table in CHR-ROM (4 KB).
+pub const PATTERN_TABLE_SIZE: usize = TILE_SIZE * PATTERN_TABLE_TILES;
+/// Size of the game's CHR-ROM, both pattern tables (8 KB).
+pub const CHR_SIZE: usize = 2 * PATTERN_TABLE_SIZE;
+
+enum_mapped!(
+    pub PatternTableKind (u8) {
+        0 => Sprites,
+        1 => Background,
+    }
+);
+
+impl PatternTableKind {
+    /// Offset of the pattern table in the game's CHR-ROM (the game keeps
+    /// sprites at PPU $0000 and the background at PPU $1000).
+    pub fn offset(&self) -> usize {
+        self.value() as usize * PATTERN_TABLE_SIZE
+    }
+}
+
+/**
+ * An 8x8 tile, one color index (0-3) per pixel, stored row by row.
+ *
+ * From: https://www.nesdev.org/wiki/PPU_pattern_tables
+ *
+ * Tiles are stored as 2 bitplanes of 8 bytes each, one byte per row with the
+ * leftmost pixel in bit 7.  The first plane has bit 0 of each color index
+ * and the second plane bit 1.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tile {
+    pub pixels: [[u8; 8]; 8],
+}
+
+impl Tile {
+    pub fn new(pixels: [[u8; 8]; 8]) -> Self {
+        Self { pixels }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < TILE_SIZE {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let mut pixels = [[0; 8]; 8];
+        for (y, row) in pixels.iter_mut().enumerate() {
+            let low = bytes[y];
+            let high = bytes[y + 8];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let bit = 7 - x;
+                *pixel = (low >> bit & 1) | (high >> bit & 1) << 1;
+            }
+        }
+
+        Ok(Self { pixels })
+    }
+
+    /// Encode the tile back into its 2 bitplanes, only the low 2 bits of
+    /// each pixel are kept.
+    pub fn to_bytes(&self) -> [u8; TILE_SIZE] {
+        let mut bytes = [0; TILE_SIZE];
+        for (y, row) in self.pixels.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                let bit = 7 - x;
+                bytes[y] |= (pixel & 1) << bit;
+                bytes[y + 8] |= (pixel >> 1 & 1) << bit;
+            }
+        }
+
+        bytes
+    }
+
+    /**
+     * Get the color index (0-3) of a pixel, 0 is the backdrop color for
+     * background tiles and transparent for sprites.
+     */
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y][x]
+    }
+}
+
+/// A pattern table: 256 tiles, indexed the same way as the game does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternTable {
+    pub tiles: Vec<Tile>,
+}
+
+impl PatternTable {
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if bytes.len() < PATTERN_TABLE_SIZE {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let tiles = bytes[..PATTERN_TABLE_SIZE]
+            .chunks_exact(TILE_SIZE)
+            .map(Tile::from_bytes)
+            .collect::<ParseResult<_>>()?;
+
+        Ok(Self { tiles })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.tiles.iter().flat_map(|tile| tile.to_bytes()).collect()
+    }
+
+    pub fn tile(&self, index: u8) -> &Tile {
+        &self.tiles[index as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The "½" from the nesdev wiki example.
+    const TILE_BYTES: [u8; TILE_SIZE] = [
+        0x41, 0xc2, 0x44, 0x48, 0x10, 0x20, 0x40, 0x80, // plane 0
+        0x01, 0x02, 0x04, 0x08, 0x16, 0x21, 0x42, 0x87, // plane 1
+    ];
+
+    #[test]
+    fn test_tile() {
+        let tile = Tile::from_bytes(&TILE_BYTES).unwrap();
+
+        assert_eq!(tile.pixels[0], [0, 1, 0, 0, 0, 0, 0, 3]);
+        assert_eq!(tile.pixels[4], [0, 0, 0, 3, 0, 2, 2, 0]);
+        assert_eq!(tile.pixels[7], [3, 0, 0, 0, 0, 2, 2, 2]);
+        assert_eq!(tile.pixel(7, 0), 3);
+
+        assert_eq!(tile.to_bytes(), TILE_BYTES);
+    }
+
+    #[test]
+    fn test_pattern_table() {
+        let bytes: Vec<u8> = (0..PATTERN_TABLE_SIZE).map(|n| n as u8).collect();
+        let table = PatternTable::from_bytes(&bytes).unwrap();
+
+        assert_eq!(table.tiles.len(), PATTERN_TABLE_TILES);
+        assert_eq!(table.to_bytes(), bytes);
+
+        assert!(PatternTable::from_bytes(&bytes[1..]).is_err());
+        assert!(Tile::from_bytes(&TILE_BYTES[1..]).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+use anyhow::{bail, ensure, Result};
+
+use crate::{
+    INesHeader, Offset, ParseError, ParseResult, TvSystem, INES_HEADER_SIZE,
+    INES_MAGIC,
+};
+
+const PRG_IMAGE_SIZE: usize = 0x8000;
+const PRG_ROM_START: u16 = 0x8000;
+
+/// iNES header used by the original dump, added to headerless dumps.
+const SMB1_INES_HEADER: [u8; INES_HEADER_SIZE] =
+    [0x4e, 0x45, 0x53, 0x1a, 0x02, 0x01, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+const SMB1_HEADERLESS_SIZE: usize = 40960;
+
+/// CPU address of the WorldAddrOffsets table in the original release.
+const WORLD_LEVEL_TABLES: u16 = 0x9cb4;
+
+/// A known good dump of one of the revisions.
+struct KnownRom {
+    /// md5 of the whole file.
+    md5: &'static str,
+    /// md5 of PRG-ROM and CHR-ROM without the header, which headerless dumps
+    /// and dumps with a different header have in common.
+    headerless_md5: &'static str,
+    revision: RomRevision,
+    /// Where the game is in the dump, with the header the md5 is for.
+    profile: RomProfile,
+}
+
+/// Dumps `Rom::new` accepts, anything else has to be loaded as unverified.
+const KNOWN_ROMS: &[KnownRom] = &[KnownRom {
+    md5: "811b027eaf99c2def7b933c5208636de",
+    headerless_md5: "8e3630186e35d477231bf8fd50e54cdd",
+    revision: RomRevision::World,
+    profile: RomProfile {
+        prg_offset: INES_HEADER_SIZE,
+        level_tables: WORLD_LEVEL_TABLES,
+    },
+}];
+
+// where each level pointer table starts, relative to WorldAddrOffsets
+const AREA_ADDR_OFFSETS_START: u16 = 8;
+const ENEMY_ADDR_H_OFFSETS_START: u16 = 44;
+const ENEMY_DATA_ADDR_LOW_START: u16 = 48;
+const ENEMY_DATA_ADDR_HIGH_START: u16 = 82;
+const AREA_DATA_H_OFFSETS_START: u16 = 116;
+const AREA_DATA_ADDR_LOW_START: u16 = 120;
+const AREA_DATA_ADDR_HIGH_START: u16 = 154;
+
+// the base index tables don't change between revisions (or most hacks), so
+// they are used to find the level pointer tables
+const ENEMY_ADDR_H_OFFSETS_SIGNATURE: &[u8] = &[0x1f, 0x06, 0x1c, 0x00];
+const AREA_DATA_H_OFFSETS_SIGNATURE: &[u8] = &[0x00, 0x03, 0x19, 0x1c];
+
+/// A release of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RomRevision {
+    /// Super Mario Bros. (Japan, USA)
+    World,
+    /// Super Mario Bros. (Europe), the PAL release
+    Europe,
+    /// Super Mario Bros. / Duck Hunt (USA) multicart
+    DuckHunt,
+    /// Super Mario Bros. / Duck Hunt / World Class Track Meet (USA) multicart
+    DuckHuntTrackMeet,
+    /// Something laid out like the game that isn't known (rom hacks)
+    Unknown,
+}
+
+/**
+ * Where the game lives in a rom file.
+ *
+ * All of the level pointer tables are found relative to WorldAddrOffsets,
+ * since they are stored back to back:
+ *
+ * | table              | size |
+ * |--------------------|------|
+ * | WorldAddrOffsets   | 8    |
+ * | AreaAddrOffsets    | 36   |
+ * | EnemyAddrHOffsets  | 4    |
+ * | EnemyDataAddrLow   | 34   |
+ * | EnemyDataAddrHigh  | 34   |
+ * | AreaDataHOffsets   | 4    |
+ * | AreaDataAddrLow    | 34   |
+ * | AreaDataAddrHigh   | 34   |
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomProfile {
+    /// File offset of the game's 32 KB PRG-ROM image (mapped to $8000).
+    pub prg_offset: Offset,
+    /// CPU address of the first level pointer table (WorldAddrOffsets).
+    pub level_tables: u16,
+}
+
+impl RomProfile {
+    pub fn world_addr_offsets(&self) -> u16 {
+        self.level_tables
+    }
+
+    pub fn area_addr_offsets(&self) -> u16 {
+        self.level_tables + AREA_ADDR_OFFSETS_START
+    }
+
+    pub fn enemy_addr_h_offsets(&self) -> u16 {
+        self.level_tables + ENEMY_ADDR_H_OFFSETS_START
+    }
+
+    pub fn enemy_data_addr_low(&self) -> u16 {
+        self.level_tables + ENEMY_DATA_ADDR_LOW_START
+    }
+
+    pub fn enemy_data_addr_high(&self) -> u16 {
+        self.level_tables + ENEMY_DATA_ADDR_HIGH_START
+    }
+
+    pub fn area_data_h_offsets(&self) -> u16 {
+        self.level_tables + AREA_DATA_H_OFFSETS_START
+    }
+
+    pub fn area_data_addr_low(&self) -> u16 {
+        self.level_tables + AREA_DATA_ADDR_LOW_START
+    }
+
+    pub fn area_data_addr_high(&self) -> u16 {
+        self.level_tables + AREA_DATA_ADDR_HIGH_START
+    }
+
+    /**
+     * The 32 KB PRG-ROM bank the game is in, multicarts keep the game's
+     * graphics in the 8 KB CHR-ROM bank with the same number.
+     */
+    pub fn bank(&self, header: &INesHeader) -> usize {
+        (self.prg_offset - header.prg_rom_range().start) / PRG_IMAGE_SIZE
+    }
+
+    /// Convert a CPU address in PRG-ROM into a file offset.
+    pub fn cpu_address_to_offset(&self, address: u16) -> Offset {
+        self.prg_offset + (address - PRG_ROM_START) as usize
+    }
+
+    /// Convert a CPU address read from the rom, which may not be in PRG-ROM.
+    pub fn checked_cpu_address_to_offset(
+        &self,
+        address: u16,
+    ) -> ParseResult<Offset> {
+        if address < PRG_ROM_START {
+            return Err(ParseError::InvalidAddress { address });
+        }
+
+        Ok(self.cpu_address_to_offset(address))
+    }
+
+    /// Convert a file offset into a CPU address, if it is in PRG-ROM.
+    pub fn offset_to_cpu_address(&self, offset: Offset) -> Option<u16> {
+        let address = offset.checked_sub(self.prg_offset)?;
+        (address < PRG_IMAGE_SIZE).then(|| PRG_ROM_START + address as u16)
+    }
+}
+
+/// What a rom file was identified as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomIdentity {
+    pub revision: RomRevision,
+    pub md5: String,
+    /// Whether the rom matches a known good dump.
+    pub verified: bool,
+    pub profile: RomProfile,
+}
+
+impl RomIdentity {
+    /**
+     * Identify rom data.
+     *
+     * Known dumps are matched by their md5.  Anything else is identified by
+     * its iNES header and by searching for the level pointer tables, so rom
+     * hacks (or dumps we don't have a checksum for) can still be parsed.
+     */
+    pub fn identify(data: &[u8], header: &INesHeader) -> Result<Self> {
+        ensure!(
+            data.len() >= header.file_size(),
+            "rom too small: {} bytes, header expects {} bytes",
+            data.len(),
+            header.file_size()
+        );
+
+        let md5 = format!("{:x}", md5::compute(data));
+        let contents = header.prg_rom_range().start..header.chr_rom_range().end;
+        let headerless_md5 = format!("{:x}", md5::compute(&data[contents]));
+        let known = KNOWN_ROMS.iter().find(|known| {
+            known.md5 == md5 || known.headerless_md5 == headerless_md5
+        });
+
+        let (revision, verified, profile) = match known {
+            Some(known) => {
+                // a different header can move PRG-ROM (a trainer before it)
+                let prg_offset = header.prg_rom_range().start
+                    + (known.profile.prg_offset - INES_HEADER_SIZE);
+                let profile = RomProfile { prg_offset, ..known.profile };
+                (known.revision, true, profile)
+            }
+            None => {
+                let profile = Self::find_profile(data, header)?;
+                (Self::guess_revision(header, &profile), false, profile)
+            }
+        };
+
+        Ok(Self { revision, md5, verified, profile })
+    }
+
+    /// Add the iNES header to a headerless dump, other data is left as-is.
+    pub fn normalize(mut data: Vec<u8>) -> Vec<u8> {
+        if !data.starts_with(INES_MAGIC) && data.len() == SMB1_HEADERLESS_SIZE {
+            data.splice(0..0, SMB1_INES_HEADER);
+        }
+
+        data
+    }
+
+    /**
+     * Find the level pointer tables in PRG-ROM.
+     *
+     * The game's PRG-ROM is a single 32 KB image, multicarts store it as one
+     * of their 32 KB banks, so the image is the bank the tables are found in.
+     */
+    fn find_profile(data: &[u8], header: &INesHeader) -> Result<RomProfile> {
+        let prg_rom_range = header.prg_rom_range();
+        let prg = &data[prg_rom_range.clone()];
+
+        let has_signature = |start: usize, signature: &[u8]| {
+            prg.get(start..).is_some_and(|bytes| bytes.starts_with(signature))
+        };
+        let tables = (0..prg.len()).find(|&idx| {
+            let enemy = idx + ENEMY_ADDR_H_OFFSETS_START as usize;
+            let area = idx + AREA_DATA_H_OFFSETS_START as usize;
+            has_signature(enemy, ENEMY_ADDR_H_OFFSETS_SIGNATURE)
+                && has_signature(area, AREA_DATA_H_OFFSETS_SIGNATURE)
+        });
+        let Some(tables) = tables else {
+            bail!("could not find the level pointer tables in the rom");
+        };
+
+        let bank = tables / PRG_IMAGE_SIZE * PRG_IMAGE_SIZE;
+        let prg_offset = prg_rom_range.start + bank;
+        let level_tables = PRG_ROM_START + (tables - bank) as u16;
+
+        Ok(RomProfile { prg_offset, level_tables })
+    }
+
+    /// Guess the revision of an unknown dump from its iNES header and layout.
+    fn guess_revision(
+        header: &INesHeader,
+        profile: &RomProfile,
+    ) -> RomRevision {
+        let pal = header.tv_system == TvSystem::Pal;
+        let multicart = header.prg_rom_size > PRG_IMAGE_SIZE;
+
+        // mapper 37 is also a multicart with the game (Super Mario Bros. /
+        // Tetris / Nintendo World Cup), but not one of the known revisions
+        match header.mapper {
+            66 => RomRevision::DuckHunt,
+            1 if multicart => RomRevision::DuckHuntTrackMeet,
+            0 if pal => RomRevision::Europe,
+            0 if profile.level_tables == WORLD_LEVEL_TABLES => {
+                RomRevision::World
+            }
+            _ => RomRevision::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_revision() {
+        let profile = RomProfile { prg_offset: 16, level_tables: 0x9cb4 };
+        let guess = |prg_banks: u8, flags6: u8, flags7: u8, flags9: u8| {
+            let mut bytes = SMB1_INES_HEADER.to_vec();
+            (bytes[4], bytes[6], bytes[7], bytes[9]) =
+                (prg_banks, flags6, flags7, flags9);
+            bytes.resize(0x50000, 0);
+            let header = INesHeader::from_bytes(&bytes).unwrap();
+            RomIdentity::guess_revision(&header, &profile)
+        };
+
+        assert_eq!(guess(2, 0x01, 0x00, 0), RomRevision::World);
+        assert_eq!(guess(2, 0x01, 0x00, 1), RomRevision::Europe);
+        assert_eq!(guess(4, 0x21, 0x40, 0), RomRevision::DuckHunt);
+        assert_eq!(guess(16, 0x10, 0x00, 0), RomRevision::DuckHuntTrackMeet);
+        // Super Mario Bros. / Tetris / Nintendo World Cup
+        assert_eq!(guess(8, 0x50, 0x20, 0), RomRevision::Unknown);
+    }
+
+    /// A rom with the level pointer table signatures at `level_tables` in
+    /// PRG-ROM bank `bank`.
+    fn identify(
+        header: (u8, u8, u8, u8),
+        bank: usize,
+        level_tables: u16,
+    ) -> RomIdentity {
+        let (prg_banks, flags6, flags7, flags9) = header;
+        let mut data = SMB1_INES_HEADER.to_vec();
+        (data[4], data[6], data[7], data[9]) =
+            (prg_banks, flags6, flags7, flags9);
+        data.resize(INES_HEADER_SIZE + prg_banks as usize * 0x4000 + 0x2000, 0);
+
+        let tables = INES_HEADER_SIZE
+            + bank * PRG_IMAGE_SIZE
+            + (level_tables - PRG_ROM_START) as usize;
+        for (start, signature) in [
+            (ENEMY_ADDR_H_OFFSETS_START, ENEMY_ADDR_H_OFFSETS_SIGNATURE),
+            (AREA_DATA_H_OFFSETS_START, AREA_DATA_H_OFFSETS_SIGNATURE),
+        ] {
+            let start = tables + start as usize;
+            data[start..start + signature.len()].copy_from_slice(signature);
+        }
+
+        let header = INesHeader::from_bytes(&data).unwrap();
+        RomIdentity::identify(&data, &header).unwrap()
+    }
+
+    #[test]
+    fn test_identify_world() {
+        let identity = identify((2, 0x01, 0x00, 0), 0, WORLD_LEVEL_TABLES);
+        assert_eq!(identity.revision, RomRevision::World);
+        assert!(!identity.verified);
+        assert_eq!(identity.profile, KNOWN_ROMS[0].profile);
+    }
+
+    #[test]
+    fn test_identify_europe() {
+        let identity = identify((2, 0x01, 0x00, 1), 0, 0x9cc0);
+        assert_eq!(identity.revision, RomRevision::Europe);
+        assert_eq!(
+            identity.profile,
+            RomProfile { prg_offset: INES_HEADER_SIZE, level_tables: 0x9cc0 }
+        );
+    }
+
+    #[test]
+    fn test_identify_duck_hunt() {
+        let identity = identify((4, 0x21, 0x40, 0), 1, WORLD_LEVEL_TABLES);
+        assert_eq!(identity.revision, RomRevision::DuckHunt);
+        assert_eq!(
+            identity.profile,
+            RomProfile {
+                prg_offset: INES_HEADER_SIZE + PRG_IMAGE_SIZE,
+                level_tables: WORLD_LEVEL_TABLES,
+            }
+        );
+    }
+
+    #[test]
+    fn test_identify_duck_hunt_track_meet() {
+        let identity = identify((16, 0x10, 0x00, 0), 3, WORLD_LEVEL_TABLES);
+        assert_eq!(identity.revision, RomRevision::DuckHuntTrackMeet);
+        assert_eq!(
+            identity.profile,
+            RomProfile {
+                prg_offset: INES_HEADER_SIZE + 3 * PRG_IMAGE_SIZE,
+                level_tables: WORLD_LEVEL_TABLES,
+            }
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+use crate::util::enum_mapped;
+use crate::{Offset, ParseError, ParseResult};
+
+pub const INES_HEADER_SIZE: usize = 16;
+pub const INES_MAGIC: &[u8] = b"NES\x1a";
+
+const TRAINER_SIZE: usize = 512;
+const PRG_ROM_BANK_SIZE: usize = 0x4000;
+const CHR_ROM_BANK_SIZE: usize = 0x2000;
+const PRG_RAM_BANK_SIZE: usize = 0x2000;
+
+enum_mapped!(
+    pub Mirroring (u8) {
+        0 => Horizontal,
+        1 => Vertical,
+    }
+);
+
+enum_mapped!(
+    pub TvSystem (u8) {
+        0 => Ntsc,
+        1 => Pal,
+        2 => MultipleRegion,
+        3 => Dendy,
+    }
+);
+
+/**
+ * The 16 byte header at the start of a .nes file.
+ *
+ * From: https://www.nesdev.org/wiki/INES and
+ * https://www.nesdev.org/wiki/NES_2.0
+ *
+ * | byte | contents                                             |
+ * |------|------------------------------------------------------|
+ * | 0-3  | "NES" followed by $1A                                |
+ * | 4    | PRG-ROM size (16 KB units)                           |
+ * | 5    | CHR-ROM size (8 KB units)                            |
+ * | 6    | MMMMFTBM mapper low, four-screen, trainer, battery,  |
+ * |      | mirroring                                            |
+ * | 7    | MMMMVV.. mapper middle, format version (10 = NES 2.0) |
+ * | 8    | iNES: PRG-RAM size, NES 2.0: SSSSMMMM submapper,     |
+ * |      | mapper high                                          |
+ * | 9    | iNES: TV system, NES 2.0: CCCCPPPP CHR/PRG-ROM size  |
+ * |      | high                                                 |
+ * | 10   | NES 2.0: NNNNRRRR PRG-NVRAM/PRG-RAM shift            |
+ * | 11   | NES 2.0: NNNNRRRR CHR-NVRAM/CHR-RAM shift            |
+ * | 12   | NES 2.0: ......TT timing                             |
+ *
+ * Sizes are stored in bytes, RAM sizes are only known for NES 2.0 headers
+ * (besides the iNES PRG-RAM size) and are 0 otherwise.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct INesHeader {
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub mirroring: Mirroring,
+    pub four_screen: bool,
+    pub battery: bool,
+    pub trainer: bool,
+    pub nes2: bool,
+    pub tv_system: TvSystem,
+
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+}
+
+impl INesHeader {
+    pub fn from_bytes(bytes: &[u8]) -> ParseResult<Self> {
+        if !bytes.starts_with(INES_MAGIC) {
+            let magic = &bytes[..bytes.len().min(INES_MAGIC.len())];
+            return Err(ParseError::unknown_value("iNES magic", magic));
+        }
+        if bytes.len() < INES_HEADER_SIZE {
+            return Err(ParseError::UnexpectedEof { offset: bytes.len() });
+        }
+
+        let flags6 = bytes[6];
+        let flags7 = bytes[7];
+        let nes2 = flags7 & 0b00001100 == 0b00001000;
+
+        let mut header = Self {
+            prg_rom_size: bytes[4] as usize * PRG_ROM_BANK_SIZE,
+            chr_rom_size: bytes[5] as usize * CHR_ROM_BANK_SIZE,
+            mapper: (flags6 >> 4 | flags7 & 0xf0) as u16,
+            submapper: 0,
+            mirroring: Mirroring::new(flags6 & 0b00000001),
+            four_screen: flags6 & 0b00001000 != 0,
+            battery: flags6 & 0b00000010 != 0,
+            trainer: flags6 & 0b00000100 != 0,
+            nes2,
+            tv_system: TvSystem::new(bytes[9] & 0b00000001),
+            prg_ram_size: (bytes[8].max(1)) as usize * PRG_RAM_BANK_SIZE,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+        };
+
+        if nes2 {
+            header.mapper |= ((bytes[8] & 0x0f) as u16) << 8;
+            header.submapper = bytes[8] >> 4;
+            header.prg_rom_size = Self::nes2_rom_size(
+                bytes[4],
+                bytes[9] & 0x0f,
+                PRG_ROM_BANK_SIZE,
+            );
+            header.chr_rom_size =
+                Self::nes2_rom_size(bytes[5], bytes[9] >> 4, CHR_ROM_BANK_SIZE);
+            header.tv_system = TvSystem::new(bytes[12] & 0b00000011);
+            header.prg_ram_size = Self::nes2_ram_size(bytes[10] & 0x0f);
+            header.prg_nvram_size = Self::nes2_ram_size(bytes[10] >> 4);
+            header.chr_ram_size = Self::nes2_ram_size(bytes[11] & 0x0f);
+            header.chr_nvram_size = Self::nes2_ram_size(bytes[11] >> 4);
+        }
+
+        // NES 2.0 sizes can be far larger than any file, the whole rom has
+        // to be there for the ranges to mean anything
+        let file_size = header
+            .prg_rom_range()
+            .start
+            .checked_add(header.prg_rom_size)
+            .and_then(|size| size.checked_add(header.chr_rom_size));
+        match file_size {
+            Some(file_size) if file_size <= bytes.len() => Ok(header),
+            _ => Err(ParseError::UnexpectedEof { offset: bytes.len() }),
+        }
+    }
+
+    /// File offsets of PRG-ROM (after the header and trainer, if any).
+    pub fn prg_rom_range(&self) -> Range<Offset> {
+        let start =
+            INES_HEADER_SIZE + if self.trainer { TRAINER_SIZE } else { 0 };
+        start..start.saturating_add(self.prg_rom_size)
+    }
+
+    /// File offsets of CHR-ROM (directly after PRG-ROM).
+    pub fn chr_rom_range(&self) -> Range<Offset> {
+        let start = self.prg_rom_range().end;
+        start..start.saturating_add(self.chr_rom_size)
+    }
+
+    /// Size of the whole file described by this header.
+    pub fn file_size(&self) -> usize {
+        self.chr_rom_range().end
+    }
+
+    /**
+     * NES 2.0 rom sizes use the MSB nibble from byte 9, unless it is $F in
+     * which case the LSB byte is EEEEEEMM: 2^E * (MM * 2 + 1) bytes.
+     */
+    fn nes2_rom_size(lsb: u8, msb: u8, bank_size: usize) -> usize {
+        if msb == 0x0f {
+            let exponent = (lsb >> 2) as u32;
+            let multiplier = (lsb & 0b00000011) as usize * 2 + 1;
+            2usize.saturating_pow(exponent).saturating_mul(multiplier)
+        } else {
+            ((msb as usize) << 8 | lsb as usize) * bank_size
+        }
+    }
+
+    /// NES 2.0 RAM sizes are stored as a shift count: 64 << shift bytes.
+    fn nes2_ram_size(shift: u8) -> usize {
+        if shift == 0 {
+            0
+        } else {
+            64 << shift
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ines_header() {
+        let mut bytes = vec![
+            0x4e, 0x45, 0x53, 0x1a, 0x02, 0x01, 0x01, 0x00, 0, 0, 0, 0, 0, 0,
+            0, 0,
+        ];
+        bytes.resize(40976, 0);
+        let header = INesHeader::from_bytes(&bytes).unwrap();
+
+        assert_eq!(header.prg_rom_size, 0x8000);
+        assert_eq!(header.chr_rom_size, 0x2000);
+        assert_eq!(header.mapper, 0);
+        assert_eq!(header.mirroring, Mirroring::Vertical);
+        assert_eq!(header.tv_system, TvSystem::Ntsc);
+        assert!(!header.nes2 && !header.trainer && !header.battery);
+        assert_eq!(header.prg_rom_range(), 0x10..0x8010);
+        assert_eq!(header.chr_rom_range(), 0x8010..0xa010);
+        assert_eq!(header.file_size(), 40976);
+    }
+
+    #[test]
+    fn test_nes2_header() {
+        // mapper 37, submapper 1, PAL, trainer and battery-backed PRG-RAM
+        let mut bytes = vec![
+            0x4e, 0x45, 0x53, 0x1a, 0x08, 0x10, 0x56, 0x28, 0x10, 0x00, 0x70,
+            0x00, 0x01, 0, 0, 0,
+        ];
+        bytes.resize(0x40210, 0);
+        let header = INesHeader::from_bytes(&bytes).unwrap();
+
+        assert!(header.nes2);
+        assert_eq!(header.mapper, 37);
+        assert_eq!(header.submapper, 1);
+        assert_eq!(header.prg_rom_size, 0x20000);
+        assert_eq!(header.chr_rom_size, 0x20000);
+        assert_eq!(header.tv_system, TvSystem::Pal);
+        assert_eq!(header.prg_ram_size, 0);
+        assert_eq!(header.prg_nvram_size, 0x2000);
+        assert_eq!(header.prg_rom_range(), 0x210..0x20210);
+        assert!(header.trainer && header.battery);
+    }
+
+    #[test]
+    fn test_missing_header() {
+        assert!(INesHeader::from_bytes(&[0; 16]).is_err());
+        assert!(INesHeader::from_bytes(b"NES\x1a").is_err());
+    }
+
+    #[test]
+    fn test_rom_size_too_large() {
+        // a header that doesn't match the file
+        let mut bytes = vec![
+            0x4e, 0x45, 0x53, 0x1a, 0x02, 0x01, 0x01, 0x00, 0, 0, 0, 0, 0, 0,
+            0, 0,
+        ];
+        bytes.resize(40975, 0);
+        assert_eq!(
+            INesHeader::from_bytes(&bytes),
+            Err(ParseError::UnexpectedEof { offset: 40975 })
+        );
+
+        // NES 2.0 exponent sizes that don't fit in memory at all
+        let mut bytes = vec![
+            0x4e, 0x45, 0x53, 0x1a, 0xff, 0xff, 0x00, 0x08, 0x00, 0xff, 0, 0,
+            0, 0, 0, 0,
+        ];
+        bytes.resize(116, 0);
+        assert_eq!(
+            INesHeader::from_bytes(&bytes),
+            Err(ParseError::UnexpectedEof { offset: 116 })
+        );
+    }
+}
@@ -1,44 +1,55 @@
-type Offset = usize;
+use std::fmt;
 
-#[derive(Debug)]
-pub enum RomLevel {
-    W1_1,
-    W1_2,
-    W1_3,
-    W1_4,
-    W2_1,
-    W2_2,
-    W2_3,
-    W2_4,
-    W3_1,
-    W3_2,
-    W3_3,
-    W3_4,
-    W4_1,
-    W4_2,
-    W4_3,
-    W4_4,
-    W5_1,
-    W5_2,
-    W5_3,
-    W5_4,
-    W6_1,
-    W6_2,
-    W6_3,
-    W6_4,
-    W7_1,
-    W7_2,
-    W7_3,
-    W7_4,
-    W8_1,
-    W8_2,
-    W8_3,
-    W8_4,
-}
+use crate::util::enum_mapped;
+use crate::{ParseError, ParseResult};
+
+enum_mapped!(
+    pub RomLevel (u8) {
+        0x00 => W1_1,
+        0x01 => W1_2,
+        0x02 => W1_3,
+        0x03 => W1_4,
+        0x04 => W2_1,
+        0x05 => W2_2,
+        0x06 => W2_3,
+        0x07 => W2_4,
+        0x08 => W3_1,
+        0x09 => W3_2,
+        0x0a => W3_3,
+        0x0b => W3_4,
+        0x0c => W4_1,
+        0x0d => W4_2,
+        0x0e => W4_3,
+        0x0f => W4_4,
+        0x10 => W5_1,
+        0x11 => W5_2,
+        0x12 => W5_3,
+        0x13 => W5_4,
+        0x14 => W6_1,
+        0x15 => W6_2,
+        0x16 => W6_3,
+        0x17 => W6_4,
+        0x18 => W7_1,
+        0x19 => W7_2,
+        0x1a => W7_3,
+        0x1b => W7_4,
+        0x1c => W8_1,
+        0x1d => W8_2,
+        0x1e => W8_3,
+        0x1f => W8_4,
+    }
+);
+
+const NUM_LEVELS: u8 = 32;
 
 impl RomLevel {
-    pub fn from_name(name: &str) -> Self {
-        match name {
+    /// Every level in the game, in order.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..NUM_LEVELS).map(Self::new)
+    }
+
+    pub fn from_name(name: &str) -> ParseResult<Self> {
+        let level = match name {
             "1-1" => Self::W1_1,
             "1-2" => Self::W1_2,
             "1-3" => Self::W1_3,
@@ -71,45 +82,25 @@ impl RomLevel {
             "8-2" => Self::W8_2,
             "8-3" => Self::W8_3,
             "8-4" => Self::W8_4,
-            _ => panic!("unknown level name: {}", name),
-        }
+            _ => return Err(ParseError::unknown_value("level name", name)),
+        };
+
+        Ok(level)
+    }
+
+    /// Get the world number (0-based) for this level.
+    pub fn world(&self) -> u8 {
+        self.value() / 4
     }
 
-    /// Get the header, object, and enemy offsets for a given level.
-    pub fn get_offsets(&self) -> (Offset, Offset, Offset) {
-        match self {
-            Self::W1_1 => (0x269e, 0x26a0, 0x1f11),
-            Self::W1_2 => (0x2c45, 0x2c47, 0x20e8),
-            Self::W1_3 => (0x2703, 0x2705, 0x1f2f),
-            Self::W1_4 => (0x21bf, 0x21c1, 0x1d80),
-            Self::W2_1 => (0x27dd, 0x27df, 0x1f61),
-            Self::W2_2 => (0x2e55, 0x2e57, 0x2181),
-            Self::W2_3 => (0x2758, 0x275a, 0x1f4c),
-            Self::W2_4 => (0x229f, 0x22a1, 0x1dc0),
-            Self::W3_1 => (0x2629, 0x262b, 0x1ee0),
-            Self::W3_2 => (0x2c12, 0x2c14, 0x20c3),
-            Self::W3_3 => (0x247b, 0x247d, 0x1e69),
-            Self::W3_4 => (0x2312, 0x2314, 0x1def),
-            Self::W4_1 => (0x2547, 0x2549, 0x1eab),
-            Self::W4_2 => (0x2ce8, 0x2cea, 0x2115),
-            Self::W4_3 => (0x289f, 0x28a1, 0x1fb9),
-            Self::W4_4 => (0x2220, 0x2222, 0x1da7),
-            Self::W5_1 => (0x284b, 0x284d, 0x1f8c),
-            Self::W5_2 => (0x2aa2, 0x2aa4, 0x2045),
-            Self::W5_3 => (0x2703, 0x2705, 0x1f2f),
-            Self::W5_4 => (0x229f, 0x22a1, 0x1dc0),
-            Self::W6_1 => (0x296b, 0x296d, 0x2001),
-            Self::W6_2 => (0x259a, 0x259c, 0x1eb9),
-            Self::W6_3 => (0x2906, 0x2908, 0x1fde),
-            Self::W6_4 => (0x21bf, 0x21c1, 0x1d80),
-            Self::W7_1 => (0x2b8e, 0x2b90, 0x209e),
-            Self::W7_2 => (0x2e55, 0x2e57, 0x2181),
-            Self::W7_3 => (0x2758, 0x275a, 0x1f4c),
-            Self::W7_4 => (0x237f, 0x2381, 0x1e1a),
-            Self::W8_1 => (0x2a0f, 0x2a11, 0x200b),
-            Self::W8_2 => (0x2b15, 0x2b17, 0x2070),
-            Self::W8_3 => (0x24de, 0x24e0, 0x1e8e),
-            Self::W8_4 => (0x240a, 0x240c, 0x1e2f),
-        }
+    /// Get the level number within the world (0-based) for this level.
+    pub fn level(&self) -> u8 {
+        self.value() % 4
+    }
+}
+
+impl fmt::Display for RomLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.world() + 1, self.level() + 1)
     }
 }
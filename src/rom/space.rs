@@ -0,0 +1,331 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::{Area, Offset};
+
+/// Byte the game's unused space is filled with.
+pub const PADDING_BYTE: u8 = 0xff;
+
+/// The two kinds of level data, each stored in its own region of PRG-ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LevelDataKind {
+    /// Area headers and objects, ending with 0xFD.
+    Objects,
+    /// Enemies and pipe pointers, ending with 0xFF.
+    Enemies,
+}
+
+impl fmt::Display for LevelDataKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Objects => write!(f, "object data"),
+            Self::Enemies => write!(f, "enemy data"),
+        }
+    }
+}
+
+/// Data used by one area, or by every area pointing at the same data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataBlock {
+    pub areas: Vec<Area>,
+    pub range: Range<Offset>,
+}
+
+impl DataBlock {
+    pub fn is_shared(&self) -> bool {
+        self.areas.len() > 1
+    }
+}
+
+/**
+ * A region of the rom holding one kind of level data: from the first area's
+ * data to the end of the last area's data and any padding after it.
+ *
+ * Bytes in the region that no area points at (gaps between areas and the
+ * padding at the end) are free to be reused.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataRegion {
+    pub kind: LevelDataKind,
+    pub range: Range<Offset>,
+    /// Every block of data in the region, by offset.
+    pub blocks: Vec<DataBlock>,
+    /// Ranges in the region that no area uses.
+    pub unused: Vec<Range<Offset>>,
+}
+
+impl DataRegion {
+    /**
+     * Map out a region from where each area's data is in `rom_data`.  The
+     * region runs over the padding after the last area, up to `limit`.
+     */
+    pub fn new(
+        kind: LevelDataKind,
+        rom_data: &[u8],
+        areas: &[(Area, Range<Offset>)],
+        limit: Offset,
+    ) -> Self {
+        let mut blocks: Vec<DataBlock> = vec![];
+        for (area, range) in areas {
+            match blocks.iter_mut().find(|block| block.range == *range) {
+                Some(block) => block.areas.push(*area),
+                None => blocks.push(DataBlock {
+                    areas: vec![*area],
+                    range: range.clone(),
+                }),
+            }
+        }
+        blocks.sort_by_key(|block| (block.range.start, block.range.end));
+
+        let start = blocks.first().map_or(0, |block| block.range.start);
+        let mut end =
+            blocks.iter().map(|block| block.range.end).max().unwrap_or(start);
+        while end < limit.min(rom_data.len()) && rom_data[end] == PADDING_BYTE {
+            end += 1;
+        }
+
+        // blocks can overlap, so the unused ranges are whatever is left
+        // after the furthest any block before reached
+        let mut unused = vec![];
+        let mut reached = start;
+        for block in &blocks {
+            if block.range.start > reached {
+                unused.push(reached..block.range.start);
+            }
+            reached = reached.max(block.range.end);
+        }
+        if end > reached {
+            unused.push(reached..end);
+        }
+
+        Self { kind, range: start..end, blocks, unused }
+    }
+
+    pub fn size(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn free(&self) -> usize {
+        self.unused.iter().map(|range| range.len()).sum()
+    }
+
+    pub fn used(&self) -> usize {
+        self.size() - self.free()
+    }
+
+    /// Take ranges that aren't this region's to give out of its unused
+    /// ranges.
+    fn exclude(&mut self, ranges: &[Range<Offset>]) {
+        for excluded in ranges {
+            self.unused = self
+                .unused
+                .iter()
+                .flat_map(|range| {
+                    [
+                        range.start..range.end.min(excluded.start),
+                        range.start.max(excluded.end)..range.end,
+                    ]
+                })
+                .filter(|range| !range.is_empty())
+                .collect();
+        }
+    }
+
+    /// Blocks used by more than one area.
+    pub fn shared(&self) -> impl Iterator<Item = &DataBlock> {
+        self.blocks.iter().filter(|block| block.is_shared())
+    }
+}
+
+/// Where the level data is in a rom and how much room is left, see
+/// `Rom::space_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpaceReport {
+    pub objects: DataRegion,
+    pub enemies: DataRegion,
+}
+
+impl SpaceReport {
+    /**
+     * Map out both regions.  Each region's padding can run up to the start
+     * of the other region or to `end`, the end of the game's PRG-ROM.
+     * Where the regions overlap, the other region's data counts as used.
+     */
+    pub fn new(
+        rom_data: &[u8],
+        objects: &[(Area, Range<Offset>)],
+        enemies: &[(Area, Range<Offset>)],
+        end: Offset,
+    ) -> Self {
+        let start = |areas: &[(Area, Range<Offset>)]| {
+            areas.iter().map(|(_, range)| range.start).min().unwrap_or(end)
+        };
+        let (objects_start, enemies_start) = (start(objects), start(enemies));
+        let limit = |start: Offset, other: Offset| {
+            if other > start {
+                other
+            } else {
+                end
+            }
+        };
+
+        let mut objects = DataRegion::new(
+            LevelDataKind::Objects,
+            rom_data,
+            objects,
+            limit(objects_start, enemies_start),
+        );
+        let mut enemies = DataRegion::new(
+            LevelDataKind::Enemies,
+            rom_data,
+            enemies,
+            limit(enemies_start, objects_start),
+        );
+
+        // hacks can mix the two kinds of data: what's in use by one region
+        // isn't free in the other, and room free in both only counts for the
+        // region that starts later (the one inside the other)
+        let block_ranges = |region: &DataRegion| -> Vec<Range<Offset>> {
+            region.blocks.iter().map(|block| block.range.clone()).collect()
+        };
+        objects.exclude(&block_ranges(&enemies));
+        enemies.exclude(&block_ranges(&objects));
+        if objects.range.start < enemies.range.start {
+            objects.exclude(&enemies.unused);
+        } else {
+            enemies.exclude(&objects.unused);
+        }
+
+        Self { objects, enemies }
+    }
+
+    pub fn regions(&self) -> [&DataRegion; 2] {
+        [&self.objects, &self.enemies]
+    }
+
+    /**
+     * The room the data at `start` can use without moving anything: its
+     * current block and the padding after it, up to the next block of either
+     * kind or the end of its region.  Data that runs into the next block
+     * only gets the part before it, writing over the rest would change the
+     * other block too.
+     */
+    pub fn slot(
+        &self,
+        kind: LevelDataKind,
+        start: Offset,
+        rom_data: &[u8],
+    ) -> Range<Offset> {
+        let region = match kind {
+            LevelDataKind::Objects => &self.objects,
+            LevelDataKind::Enemies => &self.enemies,
+        };
+        let blocks = || self.regions().into_iter().flat_map(|r| &r.blocks);
+
+        let limit = blocks()
+            .map(|block| block.range.start)
+            .filter(|&other| other > start)
+            .fold(region.range.end.min(rom_data.len()), Offset::min);
+        let mut end = region
+            .blocks
+            .iter()
+            .filter(|block| block.range.start == start)
+            .map(|block| block.range.end.min(limit))
+            .max()
+            .unwrap_or(start);
+        while end < limit && rom_data[end] == PADDING_BYTE {
+            end += 1;
+        }
+
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AreaType;
+
+    #[test]
+    fn test_data_region() {
+        let ground = |index| Area::new(AreaType::Ground, index);
+        let mut rom_data = vec![0x00; 0x20];
+        rom_data[0x10..0x14].fill(PADDING_BYTE);
+
+        let region = DataRegion::new(
+            LevelDataKind::Objects,
+            &rom_data,
+            &[
+                (ground(0), 0x08..0x0c),
+                (ground(1), 0x02..0x06),
+                (ground(2), 0x08..0x0c), // shares ground-0's data
+                (ground(3), 0x0c..0x10),
+            ],
+            0x12,
+        );
+
+        assert_eq!(region.range, 0x02..0x12);
+        assert_eq!(region.blocks.len(), 3);
+        assert_eq!(region.blocks[0].range, 0x02..0x06);
+        assert_eq!(region.unused, vec![0x06..0x08, 0x10..0x12]);
+        assert_eq!((region.size(), region.used(), region.free()), (16, 12, 4));
+        assert_eq!(
+            region.shared().collect::<Vec<_>>(),
+            vec![&DataBlock {
+                areas: vec![ground(0), ground(2)],
+                range: 0x08..0x0c
+            }]
+        );
+    }
+
+    #[test]
+    fn test_space_report() {
+        let ground = |index| Area::new(AreaType::Ground, index);
+        let mut rom_data = vec![0x00; 0x20];
+        rom_data[0x08..0x20].fill(PADDING_BYTE);
+
+        // the enemy data's padding stops where the object data starts, the
+        // object data's padding at the end of PRG-ROM
+        let report = SpaceReport::new(
+            &rom_data,
+            &[(ground(0), 0x0c..0x0e)],
+            &[(ground(0), 0x00..0x08)],
+            0x1c,
+        );
+        assert_eq!(report.enemies.range, 0x00..0x0c);
+        assert_eq!(report.enemies.unused, vec![0x08..0x0c]);
+        assert_eq!(report.objects.range, 0x0c..0x1c);
+        assert_eq!(report.objects.free(), 0x0e);
+
+        // enemy data after the object data
+        let report = SpaceReport::new(
+            &rom_data,
+            &[(ground(0), 0x0c..0x0e)],
+            &[(ground(0), 0x00..0x08), (ground(1), 0x10..0x12)],
+            0x1c,
+        );
+        // room free in both only counts for the object data
+        assert_eq!(report.enemies.range, 0x00..0x12);
+        assert_eq!(report.enemies.unused, vec![0x08..0x0c]);
+        assert_eq!(report.objects.range, 0x0c..0x1c);
+        assert_eq!(report.objects.unused, vec![0x0e..0x10, 0x12..0x1c]);
+
+        // a block's slot runs over the padding up to the next block
+        assert_eq!(
+            report.slot(LevelDataKind::Objects, 0x0c, &rom_data),
+            0x0c..0x10
+        );
+        assert_eq!(
+            report.slot(LevelDataKind::Enemies, 0x10, &rom_data),
+            0x10..0x12
+        );
+        assert_eq!(
+            report.slot(LevelDataKind::Enemies, 0x00, &rom_data),
+            0x00..0x0c
+        );
+    }
+}
@@ -20,7 +20,9 @@
  * Is functionally equivalent to:
  *
  * ```
- * #[derive(Debug)]
+ * use smb1_tools::ParseError;
+ *
+ * #[derive(Debug, Clone, Copy)]
  * enum MyCoolEnum {
  *     Foo,
  *     Bar,
@@ -37,6 +39,15 @@
  *         }
  *     }
  *
+ *     pub fn try_new(val: u32) -> Result<Self, ParseError> {
+ *         match val {
+ *             12 => Ok(Self::Foo),
+ *             13 => Ok(Self::Bar),
+ *             57 => Ok(Self::Baz),
+ *             _ => Err(ParseError::unknown_value("MyCoolEnum", val)),
+ *         }
+ *     }
+ *
  *     pub fn value(&self) -> u32 {
  *         match self {
  *             Self::Foo => 12,
@@ -53,11 +64,18 @@
  * }
  * impl Eq for MyCoolEnum {}
  * ```
+ *
+ * With the `serde` feature the enum also derives `Serialize` and
+ * `Deserialize`, using the variant names.
  */
 #[macro_export]
 macro_rules! enum_mapped {
     ($v:vis $name:ident ($type:ty) { $($val:expr => $variant:ident,)* } ) => {
-        #[derive(Debug)]
+        #[derive(Debug, Clone, Copy)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize)
+        )]
         $v enum $name {
             $(
                 $variant,
@@ -75,6 +93,21 @@ macro_rules! enum_mapped {
                 }
             }
 
+            #[allow(dead_code)]
+            pub fn try_new(
+                val: $type,
+            ) -> ::std::result::Result<Self, $crate::ParseError> {
+                match val {
+                    $(
+                        $val => Ok(Self::$variant),
+                    )*
+                    _ => Err($crate::ParseError::unknown_value(
+                        stringify!($name),
+                        val,
+                    )),
+                }
+            }
+
             #[allow(dead_code)]
             pub fn value(&self) -> $type {
                 match self {
@@ -97,7 +130,7 @@ pub(crate) use enum_mapped;
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use crate::ParseError;
 
     enum_mapped!(
         MyNumberEnum (u32) {
@@ -131,6 +164,18 @@ mod tests {
         let _thing = MyNumberEnum::new(100);
     }
 
+    #[test]
+    fn test_num_4() {
+        assert_eq!(MyNumberEnum::try_new(78), Ok(MyNumberEnum::Bar));
+        assert_eq!(
+            MyNumberEnum::try_new(100),
+            Err(ParseError::UnknownValue {
+                kind: "MyNumberEnum",
+                value: "100".to_string()
+            })
+        );
+    }
+
     #[test]
     fn test_str_1() {
         assert_eq!(MyStrEnum::Foo.value(), "foo");
@@ -41,9 +41,92 @@ const LEVEL_INFORMATION: &[(RomLevel, usize, usize, usize)] = &[
     (RomLevel::W8_4, 55, 18, 7),
 ];
 
+/// [(RomLevel, (header_offset, object_offset, enemy_offset))]
+const LEVEL_OFFSETS: &[(RomLevel, (Offset, Offset, Offset))] = &[
+    (RomLevel::W1_1, (0x269e, 0x26a0, 0x1f11)),
+    (RomLevel::W1_2, (0x2c45, 0x2c47, 0x20e8)),
+    (RomLevel::W1_3, (0x2703, 0x2705, 0x1f2f)),
+    (RomLevel::W1_4, (0x21bf, 0x21c1, 0x1d80)),
+    (RomLevel::W2_1, (0x27dd, 0x27df, 0x1f61)),
+    (RomLevel::W2_2, (0x2e55, 0x2e57, 0x2181)),
+    (RomLevel::W2_3, (0x2758, 0x275a, 0x1f4c)),
+    (RomLevel::W2_4, (0x229f, 0x22a1, 0x1dc0)),
+    (RomLevel::W3_1, (0x2629, 0x262b, 0x1ee0)),
+    (RomLevel::W3_2, (0x2c12, 0x2c14, 0x20c3)),
+    (RomLevel::W3_3, (0x247b, 0x247d, 0x1e69)),
+    (RomLevel::W3_4, (0x2312, 0x2314, 0x1def)),
+    (RomLevel::W4_1, (0x2547, 0x2549, 0x1eab)),
+    (RomLevel::W4_2, (0x2ce8, 0x2cea, 0x2115)),
+    (RomLevel::W4_3, (0x289f, 0x28a1, 0x1fb9)),
+    (RomLevel::W4_4, (0x2220, 0x2222, 0x1da7)),
+    (RomLevel::W5_1, (0x284b, 0x284d, 0x1f8c)),
+    (RomLevel::W5_2, (0x2aa2, 0x2aa4, 0x2045)),
+    (RomLevel::W5_3, (0x2703, 0x2705, 0x1f2f)),
+    (RomLevel::W5_4, (0x229f, 0x22a1, 0x1dc0)),
+    (RomLevel::W6_1, (0x296b, 0x296d, 0x2001)),
+    (RomLevel::W6_2, (0x259a, 0x259c, 0x1eb9)),
+    (RomLevel::W6_3, (0x2906, 0x2908, 0x1fde)),
+    (RomLevel::W6_4, (0x21bf, 0x21c1, 0x1d80)),
+    (RomLevel::W7_1, (0x2b8e, 0x2b90, 0x209e)),
+    (RomLevel::W7_2, (0x2e55, 0x2e57, 0x2181)),
+    (RomLevel::W7_3, (0x2758, 0x275a, 0x1f4c)),
+    (RomLevel::W7_4, (0x237f, 0x2381, 0x1e1a)),
+    (RomLevel::W8_1, (0x2a0f, 0x2a11, 0x200b)),
+    (RomLevel::W8_2, (0x2b15, 0x2b17, 0x2070)),
+    (RomLevel::W8_3, (0x24de, 0x24e0, 0x1e8e)),
+    (RomLevel::W8_4, (0x240a, 0x240c, 0x1e2f)),
+];
+
+/// File offsets of the low and high bytes of an area's object data pointer.
+fn area_pointer_offsets(rom: &Rom, area: &Area) -> (Offset, Offset) {
+    let profile = rom.identity.profile;
+    let area_type = area.area_type.value() as u16;
+    let base_offset =
+        rom.cpu_address_to_offset(profile.area_data_h_offsets() + area_type);
+    let idx = (rom.rom_data[base_offset] + area.index) as u16;
+
+    (
+        rom.cpu_address_to_offset(profile.area_data_addr_low() + idx),
+        rom.cpu_address_to_offset(profile.area_data_addr_high() + idx),
+    )
+}
+
 #[test]
 fn test_rom_valid() -> Result<()> {
-    let _ = Rom::new(ROM_DATA.into())?;
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    assert_eq!(rom.identity.revision, RomRevision::World);
+    assert!(rom.identity.verified);
+
+    assert_eq!(rom.header.mapper, 0);
+    assert_eq!(rom.prg_rom().len(), 0x8000);
+    assert_eq!(rom.chr_rom().len(), 0x2000);
+    assert_eq!(rom.cpu_address_to_offset(0x9cb4), 0x1cc4);
+    assert_eq!(rom.offset_to_cpu_address(0x1cc4), Some(0x9cb4));
+    assert_eq!(rom.offset_to_cpu_address(0x8010), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_rom_identify() -> Result<()> {
+    // headerless dumps get the header added back
+    let rom = Rom::new(ROM_DATA[16..].into())?;
+    assert!(rom.rom_data == ROM_DATA, "header not restored");
+    assert!(rom.identity.verified);
+
+    // unknown dumps are only accepted as unverified
+    let mut rom_data = ROM_DATA.to_vec();
+    let last = rom_data.len() - 1;
+    rom_data[last] ^= 0xff;
+    assert!(Rom::new(rom_data.clone()).is_err());
+
+    let rom = Rom::new_unverified(rom_data)?;
+    assert!(!rom.identity.verified);
+    assert_eq!(rom.identity.revision, RomRevision::World);
+    for (level_name, ..) in LEVEL_INFORMATION {
+        rom.get_level(level_name)?;
+    }
 
     Ok(())
 }
@@ -55,7 +138,7 @@ fn test_level_data_valid() -> Result<()> {
     // loop each level
     for (name, num_objects, num_enemies, num_pipe_pointers) in LEVEL_INFORMATION
     {
-        let level = rom.get_level(name);
+        let level = rom.get_level(name)?;
 
         // ensure number of objects in level data is accurate
         let objects_len = level.object_data.objects.len();
@@ -66,7 +149,7 @@ fn test_level_data_valid() -> Result<()> {
         );
 
         // ensure number of enemies in level data is accurate
-        let enemies_len = level.enemy_data.enemies.len();
+        let enemies_len = level.enemy_data.enemies().count();
         assert_eq!(
             &enemies_len, num_enemies,
             "level {:?} wrong enemies count: found {} expected {}",
@@ -74,7 +157,7 @@ fn test_level_data_valid() -> Result<()> {
         );
 
         // ensure number of pipe pointers in level data is accurate
-        let pipe_pointers_len = level.enemy_data.pipe_pointers.len();
+        let pipe_pointers_len = level.enemy_data.pipe_pointers().count();
         assert_eq!(
             &pipe_pointers_len, num_pipe_pointers,
             "level {:?} wrong pipe pointers count: found {} expected {}",
@@ -84,3 +167,544 @@ fn test_level_data_valid() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_level_offsets_valid() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    // offsets found through the pointer tables should match the known ones
+    for (name, offsets) in LEVEL_OFFSETS {
+        let area = rom.get_level_area(name)?;
+        assert_eq!(
+            &rom.get_area_offsets(&area)?,
+            offsets,
+            "level {:?} ({}) wrong offsets",
+            name,
+            area
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_level_round_trip() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    // encoding a level should give back the exact bytes it was parsed from
+    for (name, _, _, _) in LEVEL_INFORMATION {
+        let area = rom.get_level_area(name)?;
+        let (header_offset, _, enemy_offset) = rom.get_area_offsets(&area)?;
+        let (area_data, enemy_data) = rom.get_level(name)?.to_bytes();
+
+        let original_area_data =
+            &rom.rom_data[header_offset..header_offset + area_data.len()];
+        assert_eq!(
+            area_data, original_area_data,
+            "level {:?} area data mismatch",
+            name
+        );
+
+        let original_enemy_data =
+            &rom.rom_data[enemy_offset..enemy_offset + enemy_data.len()];
+        assert_eq!(
+            enemy_data, original_enemy_data,
+            "level {:?} enemy data mismatch",
+            name
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_level_write_back() -> Result<()> {
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    // writing every level back unchanged should leave the rom untouched
+    for (name, _, _, _) in LEVEL_INFORMATION {
+        let level = rom.get_level(name)?;
+        rom.set_level(name, &level)?;
+    }
+    assert!(rom.rom_data == ROM_DATA, "rom data changed");
+
+    // a smaller level fits in place and reads back with the change
+    let mut level = rom.get_level(&RomLevel::W1_1)?;
+    let num_objects = level.object_data.objects.len();
+    level.object_data.objects.pop();
+    rom.set_level(&RomLevel::W1_1, &level)?;
+    let level = rom.get_level(&RomLevel::W1_1)?;
+    assert_eq!(level.object_data.objects.len(), num_objects - 1);
+
+    // and the room it gave up can be used again
+    let original = Rom::new(ROM_DATA.into())?;
+    rom.set_level(&RomLevel::W1_1, &original.get_level(&RomLevel::W1_1)?)?;
+    assert!(rom.rom_data == ROM_DATA, "rom data changed");
+
+    // a larger level does not fit
+    let mut level = rom.get_level(&RomLevel::W1_1)?;
+    let object = LevelObject::from_bytes(&[0x00, 0x00])?;
+    level.object_data.objects.push(object);
+    let object = LevelObject::from_bytes(&[0x00, 0x00])?;
+    level.object_data.objects.push(object);
+    assert!(rom.set_level(&RomLevel::W1_1, &level).is_err());
+
+    // the saved file is the rom data as-is
+    let path = std::env::temp_dir().join("smb1-tools-write-back.nes");
+    rom.save(&path)?;
+    assert!(std::fs::read(&path)? == rom.rom_data, "saved rom mismatch");
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_level_diff() -> Result<()> {
+    let original = Rom::new(ROM_DATA.into())?;
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    for level_name in RomLevel::all() {
+        let level = rom.get_level(&level_name)?;
+        assert_eq!(level.diff(&level), vec![], "{}", level_name);
+    }
+
+    // removing an object shows up as just that object removed
+    let mut level = rom.get_level(&RomLevel::W1_1)?;
+    let positions = level.object_data.positions();
+    let (idx, object) = level
+        .object_data
+        .objects
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, object)| !object.new_page_flag)
+        .unwrap();
+    let expected = LevelChange::Removed {
+        entry: LevelEntry::Object(object.kind.clone()),
+        position: positions[idx],
+    };
+    level.object_data.objects.remove(idx);
+    rom.set_level(&RomLevel::W1_1, &level)?;
+
+    let changes = original
+        .get_level(&RomLevel::W1_1)?
+        .diff(&rom.get_level(&RomLevel::W1_1)?);
+    assert_eq!(changes, vec![expected]);
+
+    Ok(())
+}
+
+#[test]
+fn test_space_report() -> Result<()> {
+    let mut rom = Rom::new(ROM_DATA.into())?;
+    let report = rom.space_report()?;
+
+    // the enemy data comes right after the level pointer tables, then the
+    // object data
+    assert_eq!(report.enemies.range.start, 0x1d80);
+    assert_eq!(report.objects.range.start, 0x21bf);
+    for region in report.regions() {
+        assert_eq!(region.used() + region.free(), region.size());
+    }
+    for (name, (header_offset, _, enemy_offset)) in LEVEL_OFFSETS {
+        assert!(
+            report
+                .objects
+                .blocks
+                .iter()
+                .any(|block| block.range.start == *header_offset),
+            "{} object data not found",
+            name
+        );
+        assert!(
+            report
+                .enemies
+                .blocks
+                .iter()
+                .any(|block| block.range.start == *enemy_offset),
+            "{} enemy data not found",
+            name
+        );
+    }
+
+    // a smaller level leaves the bytes it no longer uses free
+    let mut level = rom.get_level(&RomLevel::W1_1)?;
+    level.object_data.objects.pop();
+    rom.set_level(&RomLevel::W1_1, &level)?;
+    let smaller = rom.space_report()?;
+    assert_eq!(smaller.objects.free(), report.objects.free() + 2);
+    assert_eq!(smaller.enemies, report.enemies);
+
+    Ok(())
+}
+
+#[test]
+fn test_level_repack() -> Result<()> {
+    let original = Rom::new(ROM_DATA.into())?;
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    // make some room in 8-4
+    let mut smaller = rom.get_level(&RomLevel::W8_4)?;
+    smaller.object_data.objects.truncate(smaller.object_data.objects.len() - 3);
+    smaller.enemy_data.entries.pop();
+    rom.set_level(&RomLevel::W8_4, &smaller)?;
+
+    // and use it for 1-1, which no longer fits in place
+    let mut larger = rom.get_level(&RomLevel::W1_1)?;
+    let object = LevelObject::from_bytes(&[0x00, 0x00])?;
+    larger.object_data.objects.extend([object.clone(), object]);
+    let enemy = larger.enemy_data.entries[0].clone();
+    larger.enemy_data.entries.push(enemy);
+    assert!(rom.set_level(&RomLevel::W1_1, &larger).is_err());
+    rom.set_level_repacked(&RomLevel::W1_1, &larger)?;
+
+    assert_eq!(rom.get_level(&RomLevel::W1_1)?, larger);
+    assert_eq!(rom.get_level(&RomLevel::W8_4)?, smaller);
+    for area in original.areas()? {
+        let level = rom.get_area(&area)?;
+        if level != larger && level != smaller {
+            assert_eq!(level, original.get_area(&area)?, "{} changed", area);
+        }
+    }
+
+    // the free space is all at the end now
+    let report = rom.space_report()?;
+    for region in report.regions() {
+        assert!(region.unused.len() <= 1, "{} has gaps", region.kind);
+    }
+
+    // nothing changes when the data doesn't fit
+    let rom_data = rom.rom_data.clone();
+    let object = LevelObject::from_bytes(&[0x00, 0x00])?;
+    larger.object_data.objects.extend(vec![object; 0x1000]);
+    assert!(rom.set_level_repacked(&RomLevel::W1_1, &larger).is_err());
+    assert!(rom.rom_data == rom_data, "rom data changed");
+
+    Ok(())
+}
+
+#[test]
+fn test_level_repack_keeps_other_data() -> Result<()> {
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    // something that isn't padding in the room 8-4 gives up, like a hack's
+    // code
+    let mut smaller = rom.get_level(&RomLevel::W8_4)?;
+    smaller.object_data.objects.truncate(smaller.object_data.objects.len() - 3);
+    rom.set_level(&RomLevel::W8_4, &smaller)?;
+    let (header_offset, _, _) =
+        rom.get_area_offsets(&rom.get_level_area(&RomLevel::W8_4)?)?;
+    let kept = header_offset + smaller.to_bytes().0.len() + 2;
+    rom.rom_data[kept..kept + 2].copy_from_slice(&[0x4c, 0x00]);
+
+    let mut larger = rom.get_level(&RomLevel::W1_1)?;
+    let object = LevelObject::from_bytes(&[0x00, 0x00])?;
+    larger.object_data.objects.push(object);
+    rom.set_level_repacked(&RomLevel::W1_1, &larger)?;
+
+    assert_eq!(rom.get_level(&RomLevel::W1_1)?, larger);
+    assert_eq!(rom.rom_data[kept..kept + 2], [0x4c, 0x00]);
+
+    Ok(())
+}
+
+#[test]
+fn test_level_repack_shared_data() -> Result<()> {
+    let original = Rom::new(ROM_DATA.into())?;
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    // point 1-3's object data at 1-1's
+    let shared = rom.get_level_area(&RomLevel::W1_1)?;
+    let other = rom.get_level_area(&RomLevel::W1_3)?;
+    let (header_offset, _, _) = rom.get_area_offsets(&shared)?;
+    let [low, high] =
+        rom.offset_to_cpu_address(header_offset).unwrap().to_le_bytes();
+    let (low_offset, high_offset) = area_pointer_offsets(&rom, &other);
+    rom.rom_data[low_offset] = low;
+    rom.rom_data[high_offset] = high;
+    let report = rom.space_report()?;
+    assert!(report.objects.shared().any(|block| block.areas.contains(&other)));
+
+    // editing 1-1 leaves 1-3 with the data they shared
+    let mut level = rom.get_area(&shared)?;
+    let object_data = level.object_data.clone();
+    level.object_data.objects.pop();
+    rom.set_area_repacked(&shared, &level)?;
+
+    assert_eq!(rom.get_area(&shared)?, level);
+    let other_level = rom.get_area(&other)?;
+    assert_eq!(other_level.object_data, object_data);
+    assert_eq!(other_level.enemy_data, original.get_area(&other)?.enemy_data);
+    let report = rom.space_report()?;
+    assert_eq!(report.objects.shared().count(), 0);
+
+    // an area that isn't in the level data
+    let rom_data = rom.rom_data.clone();
+    let missing = Area::new(AreaType::Castle, 31);
+    let err = rom.repack(&[(missing, level)]).unwrap_err();
+    assert!(err.to_string().contains("castle-31 has no object data"));
+    assert!(rom.rom_data == rom_data, "rom data changed");
+
+    Ok(())
+}
+
+#[test]
+fn test_ips_round_trip() -> Result<()> {
+    let original = Rom::new(ROM_DATA.into())?;
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    let mut level = rom.get_level(&RomLevel::W1_1)?;
+    level.object_data.objects.pop();
+    rom.set_level(&RomLevel::W1_1, &level)?;
+
+    let patch = original.create_ips(&rom)?;
+    assert!(!patch.records.is_empty());
+    let patch = IpsPatch::from_bytes(&patch.to_bytes()?)?;
+
+    let patched = original.apply_ips(&patch)?;
+    assert!(patched.rom_data == rom.rom_data, "patched rom differs");
+    assert!(!patched.identity.verified);
+    assert_eq!(patched.get_level(&RomLevel::W1_1)?, level);
+
+    // a patch that breaks the level pointers is rejected
+    let area = original.get_level_area(&RomLevel::W1_1)?;
+    let (_, high_offset) = area_pointer_offsets(&original, &area);
+    let mut broken = original.rom_data.clone();
+    broken[high_offset] = 0x00;
+    let patch = IpsPatch::create(&original.rom_data, &broken)?;
+    assert!(original.apply_ips(&patch).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_bps_round_trip() -> Result<()> {
+    let original = Rom::new(ROM_DATA.into())?;
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    let mut level = rom.get_level(&RomLevel::W1_1)?;
+    level.object_data.objects.pop();
+    rom.set_level(&RomLevel::W1_1, &level)?;
+
+    let patch = original.create_bps(&rom);
+    let patch = BpsPatch::from_bytes(&patch.to_bytes())?;
+    let patched = original.apply_bps(&patch)?;
+    assert!(patched.rom_data == rom.rom_data, "patched rom differs");
+    assert_eq!(patched.get_level(&RomLevel::W1_1)?, level);
+
+    // the patch only applies to the rom it was made for
+    let err = rom.apply_bps(&patch).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParseError>(),
+        Some(ParseError::ChecksumMismatch { kind: "source", .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_pipe_pointers_resolve() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    // every pipe pointer should lead to an area with parseable level data
+    for (name, _, _, _) in LEVEL_INFORMATION {
+        let level = rom.get_level(name)?;
+
+        for pipe_pointer in level.enemy_data.pipe_pointers() {
+            let destination = rom.get_pipe_destination(pipe_pointer)?;
+            assert!(
+                !destination.object_data.objects.is_empty(),
+                "level {:?} pipe pointer {:?} leads to an empty area",
+                name,
+                pipe_pointer
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_all_areas_valid() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    let areas = rom.areas()?;
+    assert_eq!(areas.len(), 34, "wrong area count: {:?}", areas);
+
+    // every area should round-trip through its name and have level data
+    for area in &areas {
+        assert_eq!(&Area::from_name(&area.to_string())?, area);
+
+        rom.get_area(area)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_levels_validate() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+    let areas = rom.areas()?;
+
+    // the game can handle all of its own levels
+    for area in &areas {
+        let level = rom.get_area(area)?;
+        for issue in level.validate(&areas) {
+            assert_ne!(issue.severity, Severity::Error, "{}: {}", area, issue);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_render_levels() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    for area in rom.areas()? {
+        let level = rom.get_area(&area)?;
+        let grid = LevelRenderer::new(&level, area.area_type).render();
+
+        assert_eq!(grid.width() % PAGE_WIDTH, 0, "{}", area);
+        assert_eq!(grid.height(), LEVEL_HEIGHT, "{}", area);
+    }
+
+    // 1-1 starts on a basic floor
+    let area = rom.get_level_area(&RomLevel::W1_1)?;
+    let level = rom.get_area(&area)?;
+    let grid = LevelRenderer::new(&level, area.area_type).render();
+    assert_eq!(grid.get(0, 11), Some(MetatileKind::Ground));
+    assert_eq!(grid.get(0, 12), Some(MetatileKind::Ground));
+
+    Ok(())
+}
+
+#[test]
+fn test_render_level_image() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+    let area = rom.get_level_area(&RomLevel::W1_1)?;
+    let level = rom.get_area(&area)?;
+    let graphics = rom.level_graphics(&area, &level)?;
+    let master_palette = MasterPalette::default();
+
+    let image =
+        ImageRenderer::new(&level, area.area_type, &graphics, &master_palette)
+            .render();
+    let width = LevelRenderer::new(&level, area.area_type).width();
+    assert_eq!(image.width, width * METATILE_SIZE);
+    assert_eq!(image.height, LEVEL_HEIGHT * METATILE_SIZE);
+
+    // the top left corner is sky
+    assert_eq!(image.pixel(0, 0), master_palette.rgb(0x22));
+
+    Ok(())
+}
+
+#[test]
+fn test_chr_round_trip() -> Result<()> {
+    let mut rom = Rom::new(ROM_DATA.into())?;
+
+    // both pattern tables decode and encode back to the same bytes
+    let sprites = rom.pattern_table(PatternTableKind::Sprites)?;
+    let background = rom.pattern_table(PatternTableKind::Background)?;
+    assert_eq!(
+        [sprites.to_bytes(), background.to_bytes()].concat(),
+        rom.chr_rom()
+    );
+
+    rom.set_pattern_table(PatternTableKind::Background, &background)?;
+    assert_eq!(rom.rom_data, ROM_DATA);
+
+    // an edited tile ends up in CHR-ROM
+    let mut tile = rom.get_tile(PatternTableKind::Background, 0x24)?;
+    tile.pixels[0] = [3; 8];
+    rom.set_tile(PatternTableKind::Background, 0x24, &tile)?;
+    assert_eq!(rom.get_tile(PatternTableKind::Background, 0x24)?, tile);
+    assert_eq!(rom.chr_rom()[0x1000 + 0x24 * 16], 0xff);
+    assert_eq!(rom.chr_rom()[0x1000 + 0x24 * 16 + 8], 0xff);
+
+    Ok(())
+}
+
+#[test]
+fn test_metatile_tables() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+    let tables = rom.metatile_tables()?;
+
+    for (table, count) in tables.tables.iter().zip(METATILE_COUNTS) {
+        assert_eq!(table.len(), count);
+    }
+
+    // every metatile the renderer draws is in the tables
+    for id in 0..=255 {
+        if MetatileKind::try_new(id).is_ok() {
+            let metatile = tables.get(id).unwrap();
+            assert_eq!(metatile.palette, id >> 6);
+        }
+    }
+
+    assert_eq!(tables.get(0x00), Some(&Metatile::new([0x24; 4], 0)));
+    assert_eq!(
+        tables.get(MetatileKind::Ground.value()),
+        Some(&Metatile::new([0xb4, 0xb6, 0xb5, 0xb7], 1))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_level_palettes() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    let tables = rom.palette_tables()?;
+    assert_eq!(tables.areas[1].background[0], [0x0f, 0x29, 0x1a, 0x0f]);
+    assert_eq!(tables.mushroom, [0x22, 0x27, 0x16, 0x0f]);
+
+    // 1-1 uses the ground palettes on the blue sky
+    let area = rom.get_level_area(&RomLevel::W1_1)?;
+    let palettes = rom.level_palettes(&area, &rom.get_area(&area)?)?;
+    assert_eq!(palettes.background[0], [0x22, 0x29, 0x1a, 0x0f]);
+    assert_eq!(palettes.background_color(), 0x22);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_errors() -> Result<()> {
+    assert!(RomLevel::from_name("9-1").is_err());
+    assert!(Area::from_name("sky-1").is_err());
+    assert!(Area::from_name("ground-x").is_err());
+
+    // a level pointer outside of PRG-ROM is an error rather than a panic
+    let mut rom = Rom::new(ROM_DATA.into())?;
+    let area = rom.get_level_area(&RomLevel::W1_1)?;
+    let (_, high_offset) = area_pointer_offsets(&rom, &area);
+    rom.rom_data[high_offset] = 0x00;
+
+    let err = rom.get_level(&RomLevel::W1_1).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParseError>(),
+        Some(ParseError::InvalidAddress { .. })
+    ));
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_level_json_round_trip() -> Result<()> {
+    let rom = Rom::new(ROM_DATA.into())?;
+
+    // every area should come back unchanged from json and ron
+    for area in rom.areas()? {
+        let level = rom.get_area(&area)?;
+
+        let json = level.to_json()?;
+        assert_eq!(Level::from_json(&json)?, level, "{} json mismatch", area);
+
+        let ron = level.to_ron()?;
+        assert_eq!(Level::from_ron(&ron)?, level, "{} ron mismatch", area);
+    }
+
+    Ok(())
+}